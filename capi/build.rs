@@ -0,0 +1,25 @@
+extern crate cbindgen;
+
+use std::env;
+use std::path::Path;
+
+// Generates the header into OUT_DIR. Setting CHANGEPOINT_UPDATE_HEADER also refreshes the
+// checked-in include/changepoint.h, which is otherwise never touched by a build.
+fn main() {
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR");
+    let out_dir = env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    let config = cbindgen::Config::from_file(Path::new(&crate_dir).join("cbindgen.toml"))
+        .expect("cbindgen.toml is valid");
+    let bindings = cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("the C API can be expressed in a header");
+    bindings.write_to_file(Path::new(&out_dir).join("changepoint.h"));
+    if env::var_os("CHANGEPOINT_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(Path::new(&crate_dir).join("include").join("changepoint.h"));
+    }
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=CHANGEPOINT_UPDATE_HEADER");
+}
@@ -0,0 +1,37 @@
+/* Reads one number per line from stdin and prints the most likely changepoint.
+ *
+ *   cargo build --release -p changepoint-capi
+ *   cc -Icapi/include capi/examples/detect.c target/release/libchangepoint_capi.a \
+ *      -lpthread -ldl -lm -o detect
+ */
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "changepoint.h"
+
+int main(void) {
+  size_t len = 0, capacity = 1024;
+  double *values = malloc(capacity * sizeof(double));
+  while (values != NULL && scanf("%lf", &values[len]) == 1) {
+    if (++len == capacity) {
+      capacity *= 2;
+      values = realloc(values, capacity * sizeof(double));
+    }
+  }
+  if (values == NULL) {
+    return 1;
+  }
+
+  ChangepointEDMX *detector = changepoint_edmx_new(30);
+  ChangepointPermutationTestResult result;
+  ChangepointStatus status =
+      changepoint_permutation_test(detector, values, len, 199, 4660, &result);
+  if (status == CHANGEPOINT_STATUS_OK) {
+    printf("changepoint %zu, p-value %.5f\n", result.changepoint_index, result.p_value);
+  } else {
+    fprintf(stderr, "error %d: %s\n", status, changepoint_last_error_message());
+  }
+  changepoint_edmx_free(detector);
+  free(values);
+  return status == CHANGEPOINT_STATUS_OK ? 0 : 1;
+}
@@ -0,0 +1,119 @@
+#ifndef CHANGEPOINT_H
+#define CHANGEPOINT_H
+
+/* Generated by cbindgen from capi/src/lib.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The outcome of a call, with one code for each kind of error the library reports.
+ *
+ * Codes are never renumbered; new kinds of error are appended after the existing ones.
+ */
+typedef enum ChangepointStatus {
+  CHANGEPOINT_STATUS_OK = 0,
+  CHANGEPOINT_STATUS_NULL_POINTER = 1,
+  CHANGEPOINT_STATUS_PANIC = 2,
+  CHANGEPOINT_STATUS_NON_FINITE_VALUE = 3,
+  CHANGEPOINT_STATUS_NOT_ENOUGH_VALUES = 4,
+  CHANGEPOINT_STATUS_PERMUTATION_NEVER_RAN = 5,
+  CHANGEPOINT_STATUS_INVALID_PARAMETER = 6,
+  CHANGEPOINT_STATUS_UNPARSABLE_INPUT = 7,
+  CHANGEPOINT_STATUS_MISSING_COLUMN = 8,
+  CHANGEPOINT_STATUS_NOT_ENOUGH_PERIODS = 9,
+  CHANGEPOINT_STATUS_LENGTH_MISMATCH = 10,
+  CHANGEPOINT_STATUS_UNORDERED_TIMESTAMPS = 11,
+  CHANGEPOINT_STATUS_DUPLICATE_TIMESTAMP = 12,
+  CHANGEPOINT_STATUS_EMPTY_BUCKET = 13,
+  CHANGEPOINT_STATUS_DIMENSION_MISMATCH = 14,
+  CHANGEPOINT_STATUS_UNSUPPORTED_SCHEMA_VERSION = 15,
+  CHANGEPOINT_STATUS_IO = 16,
+  CHANGEPOINT_STATUS_OTHER = 17,
+  CHANGEPOINT_STATUS_CANCELLED = 18,
+  CHANGEPOINT_STATUS_PERMUTATION_TEST_CANCELLED = 19,
+  CHANGEPOINT_STATUS_NO_ASYMPTOTIC_DISTRIBUTION = 20,
+  CHANGEPOINT_STATUS_UNKNOWN_DETECTOR = 21,
+  CHANGEPOINT_STATUS_TOO_MANY_VALUES = 22,
+} ChangepointStatus;
+
+/**
+ * An EDM-X detector.
+ */
+typedef struct ChangepointEDMX ChangepointEDMX;
+
+/**
+ * The most likely changepoint, as an index into the observations.
+ */
+typedef struct ChangepointCandidate {
+  size_t changepoint_index;
+  double statistic;
+} ChangepointCandidate;
+
+typedef struct ChangepointPermutationTestResult {
+  size_t changepoint_index;
+  double p_value;
+} ChangepointPermutationTestResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an EDM-X detector requiring at least `delta` observations on either side of a
+ * changepoint. The handle must be released with `changepoint_edmx_free`.
+ */
+struct ChangepointEDMX *changepoint_edmx_new(size_t delta);
+
+/**
+ * Releases a detector. Passing null does nothing.
+ *
+ * # Safety
+ *
+ * `detector` must be null or a handle from `changepoint_edmx_new` that was not yet freed.
+ */
+void changepoint_edmx_free(struct ChangepointEDMX *detector);
+
+/**
+ * Finds the most likely changepoint among `len` observations, which must all be finite.
+ *
+ * # Safety
+ *
+ * `detector` must be a live handle, `values` must point to `len` doubles and `out` must point
+ * to writable memory for a `ChangepointCandidate`.
+ */
+enum ChangepointStatus changepoint_edmx_find_candidate(const struct ChangepointEDMX *detector,
+                                                       const double *values,
+                                                       size_t len,
+                                                       struct ChangepointCandidate *out);
+
+/**
+ * Runs a permutation test with `num_permutations` shuffles of the observations, drawn from a
+ * generator seeded with `seed`.
+ *
+ * # Safety
+ *
+ * `detector` must be a live handle, `values` must point to `len` doubles and `out` must point
+ * to writable memory for a `ChangepointPermutationTestResult`.
+ */
+enum ChangepointStatus changepoint_permutation_test(const struct ChangepointEDMX *detector,
+                                                    const double *values,
+                                                    size_t len,
+                                                    size_t num_permutations,
+                                                    uint64_t seed,
+                                                    struct ChangepointPermutationTestResult *out);
+
+/**
+ * The message of the last error on this thread, or null if the last call succeeded. The
+ * string is owned by the library and valid until the next call on this thread.
+ */
+const char *changepoint_last_error_message(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CHANGEPOINT_H */
@@ -0,0 +1,246 @@
+//! A C API for EDM-X and the permutation test, declared in the generated
+//! `include/changepoint.h`.
+//!
+//! Detectors are opaque handles created by `changepoint_edmx_new` and released with
+//! `changepoint_edmx_free`. Every other function returns a `ChangepointStatus`, writing its
+//! result through an out pointer on success. On failure, a description of the error can be read
+//! with `changepoint_last_error_message` on the same thread.
+
+extern crate changepoint;
+extern crate mersenne_twister;
+extern crate rand;
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use changepoint::{ChangePointDetector, EDMX, MissingValuePolicy, NonNaN, clean,
+                  permutation_test};
+use changepoint::errors::{Error, ErrorKind};
+use mersenne_twister::MersenneTwister;
+use rand::SeedableRng;
+
+/// The outcome of a call, with one code for each kind of error the library reports.
+///
+/// Codes are never renumbered; new kinds of error are appended after the existing ones.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangepointStatus {
+    Ok = 0,
+    NullPointer = 1,
+    Panic = 2,
+    NonFiniteValue = 3,
+    NotEnoughValues = 4,
+    PermutationNeverRan = 5,
+    InvalidParameter = 6,
+    UnparsableInput = 7,
+    MissingColumn = 8,
+    NotEnoughPeriods = 9,
+    LengthMismatch = 10,
+    UnorderedTimestamps = 11,
+    DuplicateTimestamp = 12,
+    EmptyBucket = 13,
+    DimensionMismatch = 14,
+    UnsupportedSchemaVersion = 15,
+    Io = 16,
+    Other = 17,
+    Cancelled = 18,
+    PermutationTestCancelled = 19,
+    NoAsymptoticDistribution = 20,
+    UnknownDetector = 21,
+    TooManyValues = 22,
+}
+
+impl<'a> From<&'a ErrorKind> for ChangepointStatus {
+    fn from(kind: &'a ErrorKind) -> Self {
+        match *kind {
+            ErrorKind::NaNOrInfiniteFloat(..) |
+            ErrorKind::NonFiniteValueAt(..) |
+            ErrorKind::NonFiniteInput(..) => ChangepointStatus::NonFiniteValue,
+            ErrorKind::NotEnoughValues(..) => ChangepointStatus::NotEnoughValues,
+            ErrorKind::PermutationNeverRan => ChangepointStatus::PermutationNeverRan,
+            ErrorKind::InvalidParameter(..) => ChangepointStatus::InvalidParameter,
+            ErrorKind::UnparsableInput(..) => ChangepointStatus::UnparsableInput,
+            ErrorKind::MissingColumn(..) => ChangepointStatus::MissingColumn,
+            ErrorKind::NotEnoughPeriods(..) => ChangepointStatus::NotEnoughPeriods,
+            ErrorKind::LengthMismatch(..) => ChangepointStatus::LengthMismatch,
+            ErrorKind::UnorderedTimestamps(..) => ChangepointStatus::UnorderedTimestamps,
+            ErrorKind::DuplicateTimestamp(..) => ChangepointStatus::DuplicateTimestamp,
+            ErrorKind::EmptyBucket(..) => ChangepointStatus::EmptyBucket,
+            ErrorKind::DimensionMismatch(..) => ChangepointStatus::DimensionMismatch,
+            ErrorKind::UnsupportedSchemaVersion(..) => {
+                ChangepointStatus::UnsupportedSchemaVersion
+            }
+            ErrorKind::Io(..) => ChangepointStatus::Io,
+            ErrorKind::DetectorPanicked(..) => ChangepointStatus::Panic,
+            ErrorKind::Cancelled => ChangepointStatus::Cancelled,
+            ErrorKind::PermutationTestCancelled(..) => {
+                ChangepointStatus::PermutationTestCancelled
+            }
+            ErrorKind::NoAsymptoticDistribution => ChangepointStatus::NoAsymptoticDistribution,
+            ErrorKind::UnknownDetector(..) => ChangepointStatus::UnknownDetector,
+            ErrorKind::TooManyValues(..) => ChangepointStatus::TooManyValues,
+            _ => ChangepointStatus::Other,
+        }
+    }
+}
+
+/// The most likely changepoint, as an index into the observations.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChangepointCandidate {
+    pub changepoint_index: usize,
+    pub statistic: f64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChangepointPermutationTestResult {
+    pub changepoint_index: usize,
+    pub p_value: f64,
+}
+
+/// An EDM-X detector.
+pub struct ChangepointEDMX {
+    detector: EDMX,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: Option<String>) {
+    let message = message.map(|message| {
+        CString::new(message.replace('\0', " ")).expect("interior NUL bytes were replaced")
+    });
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+// Runs `body`, recording its error message, and never lets a panic unwind into C.
+fn call<F>(body: F) -> ChangepointStatus
+where
+    F: FnOnce() -> Result<(), (ChangepointStatus, String)>,
+{
+    let (status, message) = match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => (ChangepointStatus::Ok, None),
+        Ok(Err((status, message))) => (status, Some(message)),
+        Err(_) => (
+            ChangepointStatus::Panic,
+            Some("The detector panicked, which is a bug in the changepoint library".to_string()),
+        ),
+    };
+    set_last_error(message);
+    status
+}
+
+fn library_error(error: Error) -> (ChangepointStatus, String) {
+    (ChangepointStatus::from(error.kind()), error.to_string())
+}
+
+fn null_pointer(name: &str) -> (ChangepointStatus, String) {
+    (ChangepointStatus::NullPointer, format!("{} must not be null", name))
+}
+
+unsafe fn observations(
+    values: *const f64,
+    len: usize,
+) -> Result<Vec<NonNaN<f64>>, (ChangepointStatus, String)> {
+    if values.is_null() {
+        return Err(null_pointer("values"));
+    }
+    clean(slice::from_raw_parts(values, len), MissingValuePolicy::Error)
+        .map(|cleaned| cleaned.values)
+        .map_err(library_error)
+}
+
+/// Creates an EDM-X detector requiring at least `delta` observations on either side of a
+/// changepoint. The handle must be released with `changepoint_edmx_free`.
+#[no_mangle]
+pub extern "C" fn changepoint_edmx_new(delta: usize) -> *mut ChangepointEDMX {
+    Box::into_raw(Box::new(ChangepointEDMX { detector: EDMX::new(delta) }))
+}
+
+/// Releases a detector. Passing null does nothing.
+///
+/// # Safety
+///
+/// `detector` must be null or a handle from `changepoint_edmx_new` that was not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn changepoint_edmx_free(detector: *mut ChangepointEDMX) {
+    if !detector.is_null() {
+        drop(Box::from_raw(detector));
+    }
+}
+
+/// Finds the most likely changepoint among `len` observations, which must all be finite.
+///
+/// # Safety
+///
+/// `detector` must be a live handle, `values` must point to `len` doubles and `out` must point
+/// to writable memory for a `ChangepointCandidate`.
+#[no_mangle]
+pub unsafe extern "C" fn changepoint_edmx_find_candidate(
+    detector: *const ChangepointEDMX,
+    values: *const f64,
+    len: usize,
+    out: *mut ChangepointCandidate,
+) -> ChangepointStatus {
+    call(|| {
+        let detector = detector.as_ref().ok_or_else(|| null_pointer("detector"))?;
+        let out = out.as_mut().ok_or_else(|| null_pointer("out"))?;
+        let observations = observations(values, len)?;
+        let candidate = detector.detector.find_candidate(&observations).map_err(
+            library_error,
+        )?;
+        *out = ChangepointCandidate {
+            changepoint_index: candidate.location,
+            statistic: candidate.statistic.value(),
+        };
+        Ok(())
+    })
+}
+
+/// Runs a permutation test with `num_permutations` shuffles of the observations, drawn from a
+/// generator seeded with `seed`.
+///
+/// # Safety
+///
+/// `detector` must be a live handle, `values` must point to `len` doubles and `out` must point
+/// to writable memory for a `ChangepointPermutationTestResult`.
+#[no_mangle]
+pub unsafe extern "C" fn changepoint_permutation_test(
+    detector: *const ChangepointEDMX,
+    values: *const f64,
+    len: usize,
+    num_permutations: usize,
+    seed: u64,
+    out: *mut ChangepointPermutationTestResult,
+) -> ChangepointStatus {
+    call(|| {
+        let detector = detector.as_ref().ok_or_else(|| null_pointer("detector"))?;
+        let out = out.as_mut().ok_or_else(|| null_pointer("out"))?;
+        let observations = observations(values, len)?;
+        let rng: MersenneTwister = SeedableRng::from_seed(seed);
+        let result = permutation_test(&detector.detector, rng, num_permutations, &observations)
+            .map_err(library_error)?;
+        *out = ChangepointPermutationTestResult {
+            changepoint_index: result.changepoint_index,
+            p_value: result.p_value,
+        };
+        Ok(())
+    })
+}
+
+/// The message of the last error on this thread, or null if the last call succeeded. The
+/// string is owned by the library and valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn changepoint_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error.borrow().as_ref().map_or(
+            ptr::null(),
+            |message| message.as_ptr(),
+        )
+    })
+}
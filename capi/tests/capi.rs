@@ -0,0 +1,103 @@
+extern crate changepoint;
+extern crate changepoint_capi;
+
+use std::ffi::CStr;
+use std::ptr;
+use changepoint::errors::ErrorKind;
+use changepoint_capi::*;
+
+fn shifted() -> Vec<f64> {
+    (0..120)
+        .map(|i| {
+            let level = if i < 60 { 10.0 } else { 20.0 };
+            level + (i % 5) as f64 * 0.5
+        })
+        .collect()
+}
+
+fn last_error() -> Option<String> {
+    let message = changepoint_last_error_message();
+    if message.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned())
+    }
+}
+
+#[test]
+fn detects_and_tests_through_handles() {
+    let values = shifted();
+    let detector = changepoint_edmx_new(10);
+    let mut candidate = ChangepointCandidate {
+        changepoint_index: 0,
+        statistic: 0.0,
+    };
+    let status = unsafe {
+        changepoint_edmx_find_candidate(detector, values.as_ptr(), values.len(), &mut candidate)
+    };
+    assert_eq!(status, ChangepointStatus::Ok);
+    assert!((candidate.changepoint_index as i64 - 60).abs() <= 2);
+    assert_eq!(last_error(), None);
+
+    let mut result = ChangepointPermutationTestResult {
+        changepoint_index: 0,
+        p_value: 1.0,
+    };
+    let status = unsafe {
+        changepoint_permutation_test(detector, values.as_ptr(), values.len(), 19, 7, &mut result)
+    };
+    assert_eq!(status, ChangepointStatus::Ok);
+    assert_eq!(result.changepoint_index, candidate.changepoint_index);
+    assert!(result.p_value <= 0.05);
+    unsafe { changepoint_edmx_free(detector) };
+}
+
+#[test]
+fn errors_become_status_codes_and_messages() {
+    let detector = changepoint_edmx_new(10);
+    let mut candidate = ChangepointCandidate {
+        changepoint_index: 0,
+        statistic: 0.0,
+    };
+    let mut values = shifted();
+    values[7] = f64::NAN;
+    let status = unsafe {
+        changepoint_edmx_find_candidate(detector, values.as_ptr(), values.len(), &mut candidate)
+    };
+    assert_eq!(status, ChangepointStatus::NonFiniteValue);
+    assert!(last_error().unwrap().contains("index 7"));
+
+    let status =
+        unsafe { changepoint_edmx_find_candidate(detector, values.as_ptr(), 5, &mut candidate) };
+    assert_eq!(status, ChangepointStatus::NotEnoughValues);
+
+    let status = unsafe {
+        changepoint_edmx_find_candidate(detector, ptr::null(), values.len(), &mut candidate)
+    };
+    assert_eq!(status, ChangepointStatus::NullPointer);
+    assert_eq!(last_error().unwrap(), "values must not be null");
+    unsafe { changepoint_edmx_free(detector) };
+}
+
+#[test]
+fn later_errors_have_their_own_status_codes() {
+    let statuses = [
+        ChangepointStatus::from(&ErrorKind::Cancelled),
+        ChangepointStatus::from(&ErrorKind::PermutationTestCancelled(3, 10, 0.5)),
+        ChangepointStatus::from(&ErrorKind::NoAsymptoticDistribution),
+        ChangepointStatus::from(&ErrorKind::UnknownDetector("x".into(), "edmx".into())),
+        ChangepointStatus::from(&ErrorKind::TooManyValues(10, 5)),
+    ];
+    let codes: Vec<i32> = statuses.iter().map(|&status| status as i32).collect();
+    assert_eq!(codes, vec![18, 19, 20, 21, 22]);
+    let panicked = ChangepointStatus::from(&ErrorKind::DetectorPanicked("boom".into()));
+    assert_eq!(panicked, ChangepointStatus::Panic);
+}
+
+#[test]
+fn checked_in_header_is_up_to_date() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/changepoint.h"));
+    let checked_in = include_str!("../include/changepoint.h");
+    assert!(generated == checked_in,
+            "include/changepoint.h is stale; rebuild with CHANGEPOINT_UPDATE_HEADER=1");
+}
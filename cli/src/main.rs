@@ -0,0 +1,263 @@
+extern crate changepoint;
+extern crate clap;
+extern crate mersenne_twister;
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+mod report;
+
+use std::io;
+use std::process;
+use changepoint::{ChangePointDetector, Column, Continuity, CsvInput, Dispersion, EDMScale, EDMX,
+                  KCP, Kernel, NonNaN, PermutationTestOptions, Progress, TrendDetector,
+                  permutation_test_with_options};
+use changepoint::errors::*;
+use clap::{App, Arg, ArgMatches};
+use mersenne_twister::MersenneTwister;
+use rand::SeedableRng;
+use report::{Report, SegmentStatistics};
+
+const DETECTORS: &[&str] = &["edmx", "edm-mad", "edm-iqr", "kcp", "trend"];
+
+fn arguments<'a, 'b>() -> App<'a, 'b> {
+    App::new("changepoint")
+        .about(
+            "Finds the most likely changepoint in a series read from a CSV/TSV file or stdin",
+        )
+        .arg(Arg::with_name("INPUT").help(
+            "File to read, or - for stdin (the default)",
+        ))
+        .arg(
+            Arg::with_name("detector")
+                .long("detector")
+                .short("d")
+                .takes_value(true)
+                .possible_values(DETECTORS)
+                .default_value("edmx")
+                .help("Changepoint detector to run"),
+        )
+        .arg(
+            Arg::with_name("delta")
+                .long("delta")
+                .takes_value(true)
+                .default_value("30")
+                .help("Minimum number of observations on either side of a changepoint"),
+        )
+        .arg(
+            Arg::with_name("permutations")
+                .long("permutations")
+                .short("n")
+                .takes_value(true)
+                .default_value("199")
+                .help("Permutations in the significance test, or 0 to skip the test"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .default_value("4660")
+                .help("Seed for the permutation test"),
+        )
+        .arg(
+            Arg::with_name("alpha")
+                .long("alpha")
+                .takes_value(true)
+                .default_value("0.05")
+                .help("Significance level the p-value is compared against"),
+        )
+        .arg(
+            Arg::with_name("column")
+                .long("column")
+                .short("c")
+                .takes_value(true)
+                .default_value("0")
+                .help("Column of values, by header name or zero-based index"),
+        )
+        .arg(
+            Arg::with_name("timestamp-column")
+                .long("timestamp-column")
+                .short("t")
+                .takes_value(true)
+                .help("Column of integer timestamps, by header name or zero-based index"),
+        )
+        .arg(Arg::with_name("header").long("header").help(
+            "The first row is a header, which is implied when a column is given by name",
+        ))
+        .arg(Arg::with_name("tsv").long("tsv").help(
+            "Fields are separated by tabs rather than commas",
+        ))
+        .arg(Arg::with_name("progress").long("progress").help(
+            "Report the progress of the permutation test on stderr",
+        ))
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .short("f")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .help("Output format"),
+        )
+}
+
+fn parse_column(column: &str) -> Column {
+    column.parse().map(Column::Index).unwrap_or_else(|_| {
+        Column::Name(column.to_string())
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(matches: &ArgMatches, name: &str) -> Result<T> {
+    let value = matches.value_of(name).expect("every numeric argument has a default");
+    value.parse().map_err(|_| {
+        ErrorKind::InvalidParameter(name.to_string(), format!("{:?} is not a number", value))
+            .into()
+    })
+}
+
+/// Values and, when a timestamp column was given, their timestamps.
+struct Input {
+    values: Vec<NonNaN<f64>>,
+    timestamps: Option<Vec<i64>>,
+}
+
+fn read_input(matches: &ArgMatches) -> Result<Input> {
+    let value_column = parse_column(matches.value_of("column").expect("column has a default"));
+    let timestamp_column = matches.value_of("timestamp-column").map(parse_column);
+    let named = |column: &Column| match *column {
+        Column::Name(_) => true,
+        Column::Index(_) => false,
+    };
+    let has_headers = matches.is_present("header") || named(&value_column) ||
+        timestamp_column.as_ref().is_some_and(named);
+    let mut input = CsvInput::new(value_column).with_headers(has_headers);
+    if matches.is_present("tsv") {
+        input = input.with_delimiter(b'\t');
+    }
+    let path = matches.value_of("INPUT").unwrap_or("-");
+    match timestamp_column {
+        Some(timestamp_column) => {
+            let input = input.with_timestamp_column(timestamp_column);
+            let series = if path == "-" {
+                input.read_series(io::stdin())?
+            } else {
+                input.read_series_from_path(path)?
+            };
+            let (timestamps, values) = series.into_parts();
+            Ok(Input {
+                values,
+                timestamps: Some(timestamps),
+            })
+        }
+        None => {
+            let values = if path == "-" {
+                input.read_values(io::stdin())?
+            } else {
+                input.read_values_from_path(path)?
+            };
+            Ok(Input {
+                values,
+                timestamps: None,
+            })
+        }
+    }
+}
+
+fn report_progress(progress: Progress) {
+    let end = if progress.completed == progress.total { "\n" } else { "" };
+    eprint!(
+        "\rPermutations: {}/{}, provisional p-value: {:.4}{}",
+        progress.completed,
+        progress.total,
+        progress.provisional_p_value(),
+        end
+    );
+}
+
+fn detect<B>(
+    detector: &B,
+    matches: &ArgMatches,
+    values: &[NonNaN<f64>],
+    timestamps: Option<Vec<i64>>,
+) -> Result<Report>
+where
+    B: ChangePointDetector<NonNaN<f64>, Statistic = NonNaN<f64>> + Send + Sync,
+{
+    let candidate = detector.find_candidate(values)?;
+    let num_permutations: usize = parse_number(matches, "permutations")?;
+    let p_value = if num_permutations == 0 {
+        None
+    } else {
+        let rng: MersenneTwister = SeedableRng::from_seed(parse_number::<u64>(matches, "seed")?);
+        let mut options = PermutationTestOptions::default();
+        if matches.is_present("progress") {
+            options = options.with_progress(report_progress);
+        }
+        let result = permutation_test_with_options(detector, rng, num_permutations, values, &options)?;
+        Some(result.p_value)
+    };
+    let alpha: f64 = parse_number(matches, "alpha")?;
+    let location = candidate.location;
+    Ok(Report {
+        detector: detector.name(),
+        observations: values.len(),
+        changepoint_index: location,
+        changepoint_timestamp: timestamps.map(|timestamps| timestamps[location]),
+        statistic: candidate.statistic.value(),
+        p_value,
+        alpha,
+        significant: p_value.map(|p_value| p_value <= alpha),
+        before: SegmentStatistics::new(values, 0, location),
+        after: SegmentStatistics::new(values, location, values.len()),
+    })
+}
+
+fn run(matches: &ArgMatches) -> Result<String> {
+    let Input { values, timestamps } = read_input(matches)?;
+    let delta: usize = parse_number(matches, "delta")?;
+    if delta == 0 {
+        return Err(
+            ErrorKind::InvalidParameter("delta".to_string(), "must be at least 1".to_string())
+                .into(),
+        );
+    }
+    let report = match matches.value_of("detector").expect("detector has a default") {
+        "edmx" => detect(&EDMX::new(delta), matches, &values, timestamps),
+        "edm-mad" => {
+            let detector = EDMScale::new(delta, Dispersion::MedianAbsoluteDeviation);
+            detect(&detector, matches, &values, timestamps)
+        }
+        "edm-iqr" => {
+            let detector = EDMScale::new(delta, Dispersion::InterquartileRange);
+            detect(&detector, matches, &values, timestamps)
+        }
+        "kcp" => {
+            let detector = KCP::new(Kernel::Gaussian(None), delta, 1);
+            detect(&detector, matches, &values, timestamps)
+        }
+        "trend" => {
+            let detector = TrendDetector::new(delta, Continuity::Discontinuous);
+            detect(&detector, matches, &values, timestamps)
+        }
+        _ => unreachable!("clap only accepts the listed detectors"),
+    }?;
+    Ok(match matches.value_of("format") {
+        Some("json") => changepoint::to_json(&report)?,
+        _ => report.to_text(),
+    })
+}
+
+fn main() {
+    let matches = arguments().get_matches();
+    match run(&matches) {
+        Ok(output) => println!("{}", output),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            for cause in error.iter().skip(1) {
+                eprintln!("caused by: {}", cause);
+            }
+            process::exit(1);
+        }
+    }
+}
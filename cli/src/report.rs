@@ -0,0 +1,105 @@
+use std::fmt::Write;
+use changepoint::NonNaN;
+
+/// Summary statistics of the observations on one side of the changepoint.
+#[derive(Clone, Debug, Serialize)]
+pub struct SegmentStatistics {
+    pub start: usize,
+    pub end: usize,
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub standard_deviation: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl SegmentStatistics {
+    /// Statistics of `values[start..end]`, which must not be empty.
+    pub fn new(values: &[NonNaN<f64>], start: usize, end: usize) -> Self {
+        let mut segment: Vec<f64> = values[start..end].iter().map(|value| value.value()).collect();
+        segment.sort_by(|a, b| a.partial_cmp(b).expect("values are never NaN"));
+        let count = segment.len() as f64;
+        let mean = segment.iter().sum::<f64>() / count;
+        let variance = segment.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / count;
+        let middle = segment.len() / 2;
+        let median = if segment.len().is_multiple_of(2) {
+            (segment[middle - 1] + segment[middle]) / 2.0
+        } else {
+            segment[middle]
+        };
+        SegmentStatistics {
+            start,
+            end,
+            count: segment.len(),
+            mean,
+            median,
+            standard_deviation: variance.sqrt(),
+            min: segment[0],
+            max: segment[segment.len() - 1],
+        }
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "{} observations [{}, {}): mean {:.4}, median {:.4}, std dev {:.4}, \
+             min {:.4}, max {:.4}",
+            self.count,
+            self.start,
+            self.end,
+            self.mean,
+            self.median,
+            self.standard_deviation,
+            self.min,
+            self.max
+        )
+    }
+}
+
+/// Everything the command line prints about a detection, which is also its JSON output.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub detector: String,
+    pub observations: usize,
+    pub changepoint_index: usize,
+    pub changepoint_timestamp: Option<i64>,
+    pub statistic: f64,
+    pub p_value: Option<f64>,
+    pub alpha: f64,
+    pub significant: Option<bool>,
+    pub before: SegmentStatistics,
+    pub after: SegmentStatistics,
+}
+
+impl Report {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        // Writing to a String cannot fail.
+        writeln!(text, "Detector: {}", self.detector).unwrap();
+        writeln!(text, "Observations: {}", self.observations).unwrap();
+        writeln!(text, "Changepoint index: {}", self.changepoint_index).unwrap();
+        if let Some(timestamp) = self.changepoint_timestamp {
+            writeln!(text, "Changepoint timestamp: {}", timestamp).unwrap();
+        }
+        writeln!(text, "Statistic: {:.5}", self.statistic).unwrap();
+        match (self.p_value, self.significant) {
+            (Some(p_value), Some(significant)) => {
+                writeln!(
+                    text,
+                    "P-value: {:.5} ({} at alpha {})",
+                    p_value,
+                    if significant {
+                        "significant"
+                    } else {
+                        "not significant"
+                    },
+                    self.alpha
+                ).unwrap()
+            }
+            _ => writeln!(text, "P-value: not tested").unwrap(),
+        }
+        writeln!(text, "Before: {}", self.before.to_text()).unwrap();
+        write!(text, "After: {}", self.after.to_text()).unwrap();
+        text
+    }
+}
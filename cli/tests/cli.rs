@@ -0,0 +1,73 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// A level shift from about 10 to about 20 after 100 observations, with timestamps every 60s.
+fn shifted_csv() -> String {
+    let mut csv = "time,latency\n".to_string();
+    for i in 0..200 {
+        let level = if i < 100 { 10.0 } else { 20.0 };
+        csv.push_str(&format!("{},{}\n", i * 60, level + (i % 7) as f64 * 0.5));
+    }
+    csv
+}
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_changepoint"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// The number following `key` in the output.
+fn number_after(output: &str, key: &str) -> i64 {
+    let start = output.find(key).unwrap() + key.len();
+    output[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn prints_changepoint_as_text_and_json() {
+    let text = run(&["--column", "latency", "-n", "19"], &shifted_csv());
+    assert!(text.status.success());
+    let text = String::from_utf8(text.stdout).unwrap();
+    assert!((number_after(&text, "Changepoint index: ") - 100).abs() <= 2);
+    assert!(text.contains("(significant at alpha 0.05)"));
+
+    let json = run(
+        &["-c", "latency", "-t", "time", "-n", "0", "--format", "json"],
+        &shifted_csv(),
+    );
+    assert!(json.status.success());
+    let json = String::from_utf8(json.stdout).unwrap();
+    let index = number_after(&json, "\"changepoint_index\":");
+    assert!((index - 100).abs() <= 2);
+    assert_eq!(number_after(&json, "\"changepoint_timestamp\":"), index * 60);
+    assert!(json.contains("\"p_value\":null"));
+    assert!(json.contains(&format!("\"before\":{{\"start\":0,\"end\":{},", index)));
+}
+
+#[test]
+fn reports_progress_on_stderr() {
+    let output = run(&["--column", "latency", "-n", "9", "--progress"], &shifted_csv());
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("\rPermutations: 1/9, "));
+    assert!(stderr.ends_with("Permutations: 9/9, provisional p-value: 0.0000\n"));
+}
+
+#[test]
+fn reports_bad_input_with_line_numbers() {
+    let output = run(&[], "1.0\n2.0\noops\n");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Line 3"));
+}
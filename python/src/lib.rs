@@ -0,0 +1,212 @@
+//! Python bindings for EDM-X and the permutation test, built as the `changepoint` module.
+//!
+//! Observations are 1-dimensional float64 numpy arrays, or lists and tuples of floats. NaN and
+//! infinite values are rejected with a `ValueError` unless a `missing` policy is given, in which
+//! case reported indices refer to the original observations.
+
+use changepoint::{ChangePointDetector, Cleaned, EDMX, MissingValuePolicy, PermutationTestOptions,
+                  Progress, clean, permutation_test_with_options};
+use mersenne_twister::MersenneTwister;
+use numpy::PyReadonlyArray1;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyList, PyTuple};
+use rand::SeedableRng;
+
+fn to_py_err(error: changepoint::errors::Error) -> PyErr {
+    match *error.kind() {
+        changepoint::errors::ErrorKind::Msg(_) |
+        changepoint::errors::ErrorKind::DetectorPanicked(_) => {
+            PyRuntimeError::new_err(error.to_string())
+        }
+        _ => PyValueError::new_err(error.to_string()),
+    }
+}
+
+fn parse_policy(missing: &str) -> PyResult<MissingValuePolicy> {
+    match missing {
+        "error" => Ok(MissingValuePolicy::Error),
+        "drop" => Ok(MissingValuePolicy::Drop),
+        "forward_fill" => Ok(MissingValuePolicy::ForwardFill),
+        "interpolate" => Ok(MissingValuePolicy::LinearInterpolate),
+        "median" => Ok(MissingValuePolicy::MedianImpute),
+        _ => Err(PyValueError::new_err(format!(
+            "missing must be one of 'error', 'drop', 'forward_fill', 'interpolate' or \
+             'median', not {:?}",
+            missing
+        ))),
+    }
+}
+
+/// Finite observations, and the number of observations before any were dropped.
+struct Observations {
+    cleaned: Cleaned<f64>,
+    original_len: usize,
+}
+
+impl Observations {
+    // The changepoint as an original index, with the half-open ranges of original indices
+    // before and after it.
+    fn locate(&self, location: usize) -> (usize, Vec<(usize, usize)>) {
+        let changepoint = self.cleaned.original_index(location);
+        (changepoint, vec![(0, changepoint), (changepoint, self.original_len)])
+    }
+}
+
+fn to_observations(observations: &Bound<'_, PyAny>, missing: &str) -> PyResult<Observations> {
+    let policy = parse_policy(missing)?;
+    let floats: Vec<f64> = if observations.is_instance_of::<PyList>() ||
+        observations.is_instance_of::<PyTuple>()
+    {
+        observations.extract()?
+    } else {
+        let array: PyReadonlyArray1<'_, f64> = observations.extract()?;
+        array.as_array().iter().cloned().collect()
+    };
+    Ok(Observations {
+        cleaned: clean(&floats, policy).map_err(to_py_err)?,
+        original_len: floats.len(),
+    })
+}
+
+/// The E-Divisive with Medians detector, with at least `delta` observations on either side of
+/// a changepoint.
+#[pyclass(name = "EDMX", module = "changepoint", frozen)]
+struct PyEDMX {
+    delta: usize,
+}
+
+/// The most likely changepoint, as an index into the observations.
+#[pyclass(name = "Candidate", module = "changepoint", frozen, get_all)]
+struct PyCandidate {
+    changepoint_index: usize,
+    statistic: f64,
+    segments: Vec<(usize, usize)>,
+}
+
+/// The most likely changepoint and the p-value of it being a real change.
+#[pyclass(name = "PermutationTestResult", module = "changepoint", frozen, get_all)]
+struct PyPermutationTestResult {
+    changepoint_index: usize,
+    p_value: f64,
+    segments: Vec<(usize, usize)>,
+}
+
+#[pymethods]
+impl PyEDMX {
+    #[new]
+    fn new(delta: usize) -> Self {
+        PyEDMX { delta }
+    }
+
+    #[getter]
+    fn delta(&self) -> usize {
+        self.delta
+    }
+
+    #[pyo3(signature = (observations, missing = "error"))]
+    fn find_candidate(
+        &self,
+        py: Python<'_>,
+        observations: &Bound<'_, PyAny>,
+        missing: &str,
+    ) -> PyResult<PyCandidate> {
+        let observations = to_observations(observations, missing)?;
+        let values = &observations.cleaned.values;
+        let candidate = py.detach(|| EDMX::new(self.delta).find_candidate(values))
+            .map_err(to_py_err)?;
+        let (changepoint_index, segments) = observations.locate(candidate.location);
+        Ok(PyCandidate {
+            changepoint_index,
+            statistic: candidate.statistic.value(),
+            segments,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("EDMX(delta={})", self.delta)
+    }
+}
+
+#[pymethods]
+impl PyCandidate {
+    fn __repr__(&self) -> String {
+        format!(
+            "Candidate(changepoint_index={}, statistic={})",
+            self.changepoint_index,
+            self.statistic
+        )
+    }
+}
+
+#[pymethods]
+impl PyPermutationTestResult {
+    fn __repr__(&self) -> String {
+        format!(
+            "PermutationTestResult(changepoint_index={}, p_value={})",
+            self.changepoint_index,
+            self.p_value
+        )
+    }
+}
+
+/// Tests whether the changepoint found by `detector` is significant, comparing it against
+/// `permutations` random shuffles of the observations drawn with the given `seed`.
+///
+/// `progress`, if given, is called with the number of completed permutations, the total and the
+/// provisional p-value as each permutation completes. Exceptions it raises are reported as
+/// unraisable rather than stopping the test.
+#[pyfunction]
+#[pyo3(signature = (
+    detector,
+    observations,
+    permutations = 199,
+    seed = 0x1234,
+    missing = "error",
+    progress = None
+))]
+fn permutation_test(
+    py: Python<'_>,
+    detector: &PyEDMX,
+    observations: &Bound<'_, PyAny>,
+    permutations: usize,
+    seed: u64,
+    missing: &str,
+    progress: Option<Py<PyAny>>,
+) -> PyResult<PyPermutationTestResult> {
+    let observations = to_observations(observations, missing)?;
+    let algorithm = EDMX::new(detector.delta);
+    let values = &observations.cleaned.values;
+    let mut options = PermutationTestOptions::default();
+    if let Some(callback) = progress {
+        options = options.with_progress(move |progress: Progress| {
+            Python::attach(|py| {
+                let arguments =
+                    (progress.completed, progress.total, progress.provisional_p_value());
+                if let Err(error) = callback.call1(py, arguments) {
+                    error.write_unraisable(py, None);
+                }
+            })
+        });
+    }
+    let result = py.detach(|| {
+        let rng: MersenneTwister = SeedableRng::from_seed(seed);
+        permutation_test_with_options(&algorithm, rng, permutations, values, &options)
+    }).map_err(to_py_err)?;
+    let (changepoint_index, segments) = observations.locate(result.changepoint_index);
+    Ok(PyPermutationTestResult {
+        changepoint_index,
+        p_value: result.p_value,
+        segments,
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "changepoint")]
+fn changepoint_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyEDMX>()?;
+    module.add_class::<PyCandidate>()?;
+    module.add_class::<PyPermutationTestResult>()?;
+    module.add_function(wrap_pyfunction!(permutation_test, module)?)?;
+    Ok(())
+}
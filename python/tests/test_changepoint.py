@@ -0,0 +1,61 @@
+import math
+import random
+
+import numpy as np
+import pytest
+
+import changepoint
+
+
+def shifted(before=150, after=150, seed=7):
+    rng = random.Random(seed)
+    return [rng.gauss(10.0, 2.0) for _ in range(before)] + [
+        rng.gauss(20.0, 2.0) for _ in range(after)
+    ]
+
+
+def test_find_candidate_accepts_arrays_and_lists():
+    observations = shifted()
+    edmx = changepoint.EDMX(20)
+    from_array = edmx.find_candidate(np.array(observations))
+    from_list = edmx.find_candidate(observations)
+    assert from_array.changepoint_index == from_list.changepoint_index
+    assert abs(from_array.changepoint_index - 150) <= 15
+    assert from_array.segments == [
+        (0, from_array.changepoint_index),
+        (from_array.changepoint_index, 300),
+    ]
+
+
+def test_permutation_test_is_reproducible_with_a_seed():
+    observations = np.array(shifted())
+    edmx = changepoint.EDMX(20)
+    first = changepoint.permutation_test(edmx, observations, permutations=19, seed=42)
+    second = changepoint.permutation_test(edmx, observations, permutations=19, seed=42)
+    assert first.p_value == second.p_value
+    assert first.p_value <= 0.05
+
+
+def test_missing_values():
+    observations = shifted()
+    observations[3] = math.nan
+    edmx = changepoint.EDMX(20)
+    with pytest.raises(ValueError, match="index 3"):
+        edmx.find_candidate(np.array(observations))
+    dropped = edmx.find_candidate(np.array(observations), missing="drop")
+    assert dropped.segments[1][1] == 300
+    with pytest.raises(ValueError):
+        edmx.find_candidate(observations, missing="ignore")
+
+
+def test_permutation_test_reports_progress():
+    reports = []
+    edmx = changepoint.EDMX(20)
+    result = changepoint.permutation_test(
+        edmx,
+        shifted(),
+        permutations=9,
+        progress=lambda completed, total, p_value: reports.append((completed, total, p_value)),
+    )
+    assert [completed for completed, _, _ in reports] == list(range(1, 10))
+    assert reports[-1] == (9, 9, result.p_value)
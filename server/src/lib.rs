@@ -0,0 +1,377 @@
+//! A small HTTP service running changepoint detection on JSON requests.
+//!
+//! `POST /detect` takes a detector configuration and observations, and responds with the best
+//! candidate changepoint:
+//!
+//! ```json
+//! {"detector": {"type": "edmx", "delta": 30}, "observations": [10.2, 9.8, ...]}
+//! ```
+//!
+//! `POST /permutation-test` additionally takes `permutations` and an optional `seed`, and
+//! responds with the `PermutationTestResult`. Responses use the versioned envelope of
+//! `changepoint::to_json`, and errors are reported as `{"error": "..."}`. The size of requests is
+//! bounded by `ServerConfig`, with a much lower limit on the observations sent to detectors whose
+//! cost grows with the square of the series length, and a limit on the total work of a
+//! permutation test.
+
+extern crate changepoint;
+extern crate mersenne_twister;
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate tiny_http;
+
+use std::any::Any;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+use changepoint::{ChangePointDetector, EDMScale, EDMX, KCP, NonNaN, TrendDetector,
+                  permutation_test, to_json};
+use changepoint::errors::*;
+use mersenne_twister::MersenneTwister;
+use rand::SeedableRng;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Limits protecting the server from oversized or overly expensive requests.
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    max_body_bytes: usize,
+    max_permutations: usize,
+    max_observations: usize,
+    max_quadratic_observations: usize,
+    max_permutation_work: usize,
+    max_concurrent_requests: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            max_body_bytes: 1 << 20,
+            max_permutations: 10_000,
+            max_observations: 100_000,
+            max_quadratic_observations: 5_000,
+            max_permutation_work: 1_000_000_000,
+            max_concurrent_requests: 4,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    pub fn with_max_permutations(mut self, max_permutations: usize) -> Self {
+        self.max_permutations = max_permutations;
+        self
+    }
+
+    /// The most observations a request may send to EDM-X, whose cost grows about linearly with
+    /// the length of the series.
+    pub fn with_max_observations(mut self, max_observations: usize) -> Self {
+        self.max_observations = max_observations;
+        self
+    }
+
+    /// The most observations a request may send to EDMScale, KCP or the trend detector, whose
+    /// time or memory grows with the square of the length of the series.
+    pub fn with_max_quadratic_observations(mut self, max_quadratic_observations: usize) -> Self {
+        self.max_quadratic_observations = max_quadratic_observations;
+        self
+    }
+
+    /// The most work a single permutation test may ask for, counting every run of the detector
+    /// on the observations and on each permutation as the number of observations for EDM-X, or
+    /// its square for the other detectors.
+    pub fn with_max_permutation_work(mut self, max_permutation_work: usize) -> Self {
+        self.max_permutation_work = max_permutation_work;
+        self
+    }
+
+    /// The number of requests handled at once, and so the number of detections sharing the
+    /// rayon pool. Further requests wait until one of them finishes.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum DetectorConfig {
+    Edmx(EDMX),
+    EdmScale(EDMScale),
+    Kcp(KCP),
+    Trend(TrendDetector),
+}
+
+impl DetectorConfig {
+    fn max_observations(&self, config: &ServerConfig) -> usize {
+        match *self {
+            DetectorConfig::Edmx(_) => config.max_observations,
+            DetectorConfig::EdmScale(_) |
+            DetectorConfig::Kcp(_) |
+            DetectorConfig::Trend(_) => config.max_quadratic_observations,
+        }
+    }
+
+    // Checked before running the detector, as a long enough series can take the whole process
+    // down by exhausting memory, which no error handling can recover from.
+    fn check_observations(
+        &self,
+        config: &ServerConfig,
+        observations: &[NonNaN<f64>],
+    ) -> Result<()> {
+        let max_observations = self.max_observations(config);
+        if observations.len() > max_observations {
+            return Err(ErrorKind::TooManyValues(observations.len(), max_observations).into());
+        }
+        Ok(())
+    }
+
+    // The work of one run of the detector, in the units of `max_permutation_work`.
+    fn run_work(&self, num_observations: usize) -> usize {
+        match *self {
+            DetectorConfig::Edmx(_) => num_observations,
+            DetectorConfig::EdmScale(_) |
+            DetectorConfig::Kcp(_) |
+            DetectorConfig::Trend(_) => num_observations.saturating_mul(num_observations),
+        }
+    }
+
+    // Bounds the permutations both on their own and by the total work of running the detector
+    // on the observations and every permutation of them.
+    fn check_permutations(
+        &self,
+        config: &ServerConfig,
+        request: &PermutationTestRequest,
+    ) -> Result<()> {
+        let num_observations = request.observations.len();
+        let runs = config.max_permutation_work / self.run_work(num_observations).max(1);
+        let max_permutations = config.max_permutations.min(runs.saturating_sub(1));
+        if request.permutations > max_permutations {
+            return Err(
+                ErrorKind::InvalidParameter(
+                    "permutations".to_string(),
+                    format!(
+                        "at most {} are allowed for {} observations",
+                        max_permutations,
+                        num_observations
+                    ),
+                ).into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct DetectRequest {
+    detector: DetectorConfig,
+    observations: Vec<NonNaN<f64>>,
+}
+
+#[derive(Deserialize)]
+struct PermutationTestRequest {
+    detector: DetectorConfig,
+    observations: Vec<NonNaN<f64>>,
+    permutations: usize,
+    #[serde(default)]
+    seed: u64,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+enum Endpoint {
+    Detect,
+    PermutationTest,
+}
+
+// A response status and its JSON body.
+type Reply = (u16, String);
+
+fn error_reply(status: u16, error: String) -> Reply {
+    let body = serde_json::to_string(&ErrorResponse { error }).expect(
+        "a struct with a string field always serializes",
+    );
+    (status, body)
+}
+
+fn run_detector<B>(detector: &B, observations: &[NonNaN<f64>]) -> Result<String>
+where
+    B: ChangePointDetector<NonNaN<f64>, Statistic = NonNaN<f64>>,
+{
+    to_json(&detector.find_candidate(observations)?)
+}
+
+fn run_permutation_test<B>(detector: &B, request: &PermutationTestRequest) -> Result<String>
+where
+    B: ChangePointDetector<NonNaN<f64>, Statistic = NonNaN<f64>> + Send + Sync,
+{
+    let rng: MersenneTwister = SeedableRng::from_seed(request.seed);
+    to_json(&permutation_test(
+        detector,
+        rng,
+        request.permutations,
+        &request.observations,
+    )?)
+}
+
+macro_rules! with_detector {
+    ($config:expr, $detector:ident => $body:expr) => {
+        match *$config {
+            DetectorConfig::Edmx(ref $detector) => $body,
+            DetectorConfig::EdmScale(ref $detector) => $body,
+            DetectorConfig::Kcp(ref $detector) => $body,
+            DetectorConfig::Trend(ref $detector) => $body,
+        }
+    };
+}
+
+/// Accepts detection requests, handling up to the configured number of them at once.
+pub struct DetectionServer {
+    server: Arc<Server>,
+    config: ServerConfig,
+}
+
+impl DetectionServer {
+    /// Listens on `address`, such as `127.0.0.1:8080`. Port 0 picks any free port.
+    pub fn bind(address: &str, config: ServerConfig) -> Result<Self> {
+        let server = Server::http(address).map_err(|error| Error::from(error.to_string()))?;
+        Ok(DetectionServer {
+            server: Arc::new(server),
+            config,
+        })
+    }
+
+    pub fn local_address(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Serves requests until the process exits, on one thread per concurrent request.
+    pub fn run(self) {
+        let workers: Vec<_> = (0..self.config.max_concurrent_requests)
+            .map(|_| {
+                let server = self.server.clone();
+                let config = self.config.clone();
+                thread::spawn(move || for request in server.incoming_requests() {
+                    handle(&config, request);
+                })
+            })
+            .collect();
+        for worker in workers {
+            if let Err(panic) = worker.join() {
+                eprintln!("error: a request handler stopped: {}", panic_message(&*panic));
+            }
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+// Turns a panic while handling one request into an error response for that request alone.
+fn guarded<F: FnOnce() -> Reply>(reply: F) -> Reply {
+    panic::catch_unwind(AssertUnwindSafe(reply)).unwrap_or_else(|panic| {
+        eprintln!("error: handling a request panicked: {}", panic_message(&*panic));
+        error_reply(500, "The request could not be handled".to_string())
+    })
+}
+
+fn handle(config: &ServerConfig, mut request: Request) {
+    let (status, body) = guarded(|| reply(config, &mut request));
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .expect("the header is valid ASCII");
+    let response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(content_type);
+    // The client may have gone away, which leaves nobody to tell.
+    let _ = request.respond(response);
+}
+
+fn reply(config: &ServerConfig, request: &mut Request) -> Reply {
+    let endpoint = match request.url() {
+        "/detect" => Endpoint::Detect,
+        "/permutation-test" => Endpoint::PermutationTest,
+        url => return error_reply(404, format!("No endpoint at {}", url)),
+    };
+    if *request.method() != Method::Post {
+        return error_reply(405, "Only POST requests are accepted".to_string());
+    }
+    let too_large = || {
+        error_reply(
+            413,
+            format!("Request bodies are limited to {} bytes", config.max_body_bytes),
+        )
+    };
+    if request.body_length().is_some_and(|length| length > config.max_body_bytes) {
+        return too_large();
+    }
+    // Chunked bodies have no declared length, so reading stops just past the limit.
+    let mut body = Vec::new();
+    if let Err(error) = request
+        .as_reader()
+        .take(config.max_body_bytes as u64 + 1)
+        .read_to_end(&mut body)
+    {
+        return error_reply(400, error.to_string());
+    }
+    if body.len() > config.max_body_bytes {
+        return too_large();
+    }
+    let result = match endpoint {
+        Endpoint::Detect => {
+            serde_json::from_slice(&body).map_err(Error::from).and_then(
+                |request: DetectRequest| {
+                    request.detector.check_observations(config, &request.observations)?;
+                    with_detector!(&request.detector, detector => {
+                        run_detector(detector, &request.observations)
+                    })
+                },
+            )
+        }
+        Endpoint::PermutationTest => {
+            serde_json::from_slice(&body).map_err(Error::from).and_then(
+                |request: PermutationTestRequest| {
+                    request.detector.check_observations(config, &request.observations)?;
+                    request.detector.check_permutations(config, &request)?;
+                    with_detector!(&request.detector, detector => {
+                        run_permutation_test(detector, &request)
+                    })
+                },
+            )
+        }
+    };
+    match result {
+        Ok(json) => (200, json),
+        Err(error) => error_reply(400, error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_become_internal_server_errors() {
+        let (status, body) = guarded(|| panic!("detector bug"));
+        assert_eq!(status, 500);
+        assert_eq!(body, r#"{"error":"The request could not be handled"}"#);
+        assert_eq!(guarded(|| (200, "{}".to_string())), (200, "{}".to_string()));
+    }
+}
@@ -0,0 +1,100 @@
+extern crate changepoint_server;
+extern crate clap;
+
+use std::process;
+use changepoint_server::{DetectionServer, ServerConfig};
+use clap::{App, Arg, ArgMatches};
+
+fn arguments<'a, 'b>() -> App<'a, 'b> {
+    App::new("changepoint-server")
+        .about(
+            "Serves changepoint detection over HTTP at POST /detect and POST /permutation-test",
+        )
+        .arg(
+            Arg::with_name("address")
+                .long("address")
+                .takes_value(true)
+                .default_value("127.0.0.1:8080")
+                .help("Address to listen on"),
+        )
+        .arg(
+            Arg::with_name("max-body-bytes")
+                .long("max-body-bytes")
+                .takes_value(true)
+                .default_value("1048576")
+                .help("Largest request body accepted"),
+        )
+        .arg(
+            Arg::with_name("max-permutations")
+                .long("max-permutations")
+                .takes_value(true)
+                .default_value("10000")
+                .help("Most permutations a single permutation test may ask for"),
+        )
+        .arg(
+            Arg::with_name("max-observations")
+                .long("max-observations")
+                .takes_value(true)
+                .default_value("100000")
+                .help("Most observations a single request may send to EDM-X"),
+        )
+        .arg(
+            Arg::with_name("max-quadratic-observations")
+                .long("max-quadratic-observations")
+                .takes_value(true)
+                .default_value("5000")
+                .help(
+                    "Most observations a single request may send to the EDMScale, KCP and trend \
+                     detectors, whose cost grows with the square of the series length",
+                ),
+        )
+        .arg(
+            Arg::with_name("max-permutation-work")
+                .long("max-permutation-work")
+                .takes_value(true)
+                .default_value("1000000000")
+                .help(
+                    "Most work a single permutation test may ask for: its runs of the detector \
+                     times the observations for EDM-X, or their square for other detectors",
+                ),
+        )
+        .arg(
+            Arg::with_name("max-concurrent-requests")
+                .long("max-concurrent-requests")
+                .takes_value(true)
+                .default_value("4")
+                .help("Requests handled at once, with later requests waiting their turn"),
+        )
+}
+
+fn parse_number(matches: &ArgMatches, name: &str) -> usize {
+    let value = matches.value_of(name).expect("every numeric argument has a default");
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("error: {:?} is not a valid value for --{}", value, name);
+        process::exit(2);
+    })
+}
+
+fn main() {
+    let matches = arguments().get_matches();
+    let config = ServerConfig::default()
+        .with_max_body_bytes(parse_number(&matches, "max-body-bytes"))
+        .with_max_permutations(parse_number(&matches, "max-permutations"))
+        .with_max_observations(parse_number(&matches, "max-observations"))
+        .with_max_quadratic_observations(parse_number(&matches, "max-quadratic-observations"))
+        .with_max_permutation_work(parse_number(&matches, "max-permutation-work"))
+        .with_max_concurrent_requests(parse_number(&matches, "max-concurrent-requests"));
+    let address = matches.value_of("address").expect("address has a default");
+    match DetectionServer::bind(address, config) {
+        Ok(server) => {
+            if let Some(address) = server.local_address() {
+                eprintln!("Listening on http://{}", address);
+            }
+            server.run();
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+extern crate changepoint_server;
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use changepoint_server::{DetectionServer, ServerConfig};
+
+fn start(config: ServerConfig) -> SocketAddr {
+    let server = DetectionServer::bind("127.0.0.1:0", config).unwrap();
+    let address = server.local_address().unwrap();
+    thread::spawn(move || server.run());
+    address
+}
+
+// Sends a request and returns the response status and body.
+fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    ).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split("\r\n\r\n").nth(1).unwrap().to_string();
+    (status, body)
+}
+
+fn shifted_observations(count: usize) -> String {
+    let values: Vec<String> = (0..count)
+        .map(|i| {
+            let level = if i < count / 2 { 10.0 } else { 20.0 };
+            (level + (i % 5) as f64 * 0.5).to_string()
+        })
+        .collect();
+    format!("[{}]", values.join(","))
+}
+
+#[test]
+fn detect_and_permutation_test_endpoints() {
+    let address = start(ServerConfig::default());
+    let (status, body) = request(
+        address,
+        "POST",
+        "/detect",
+        &format!(
+            r#"{{"detector":{{"type":"edmx","delta":10}},"observations":{}}}"#,
+            shifted_observations(120)
+        ),
+    );
+    assert_eq!(status, 200, "{}", body);
+    assert!(body.starts_with(r#"{"version":1,"data":{"statistic":"#));
+
+    let (status, body) = request(
+        address,
+        "POST",
+        "/permutation-test",
+        &format!(
+            r#"{{"detector":{{"type":"edm_scale","delta":10,"dispersion":"interquartile_range"}},
+                "observations":{},"permutations":19,"seed":7}}"#,
+            shifted_observations(120)
+        ),
+    );
+    assert_eq!(status, 200, "{}", body);
+    assert!(body.contains(r#""p_value":0.0,"#));
+}
+
+#[test]
+fn rejects_invalid_requests() {
+    let address = start(
+        ServerConfig::default()
+            .with_max_body_bytes(2000)
+            .with_max_permutations(100)
+            .with_max_quadratic_observations(100)
+            .with_max_permutation_work(100_000),
+    );
+    let edmx = format!(
+        r#""detector":{{"type":"edmx","delta":10}},"observations":{}"#,
+        shifted_observations(120)
+    );
+    let (status, body) = request(address, "POST", "/detect", r#"{"observations":[1,2,3]}"#);
+    assert_eq!(status, 400);
+    assert!(body.starts_with(r#"{"error":"#));
+    let too_short = r#"{"detector":{"type":"edmx","delta":10},"observations":[1,2,3]}"#;
+    assert_eq!(request(address, "POST", "/detect", too_short).0, 400);
+    let expensive = format!("{{{},\"permutations\":1000}}", edmx);
+    assert_eq!(request(address, "POST", "/permutation-test", &expensive).0, 400);
+    let large = format!("{{{},\"padding\":\"{}\"}}", edmx, "x".repeat(2000));
+    assert_eq!(request(address, "POST", "/detect", &large).0, 413);
+    let kcp = format!(
+        r#"{{"detector":{{"type":"kcp","kernel":"linear","min_segment_length":10,
+            "max_changepoints":1,"model_selection":"slope_heuristic"}},"observations":{}}}"#,
+        shifted_observations(120)
+    );
+    let (status, body) = request(address, "POST", "/detect", &kcp);
+    assert_eq!(status, 400);
+    assert!(body.contains("at most 100"), "{}", body);
+    let kcp = format!(
+        r#"{{"detector":{{"type":"kcp","kernel":"linear","min_segment_length":10,
+            "max_changepoints":1,"model_selection":"slope_heuristic"}},"observations":{},
+            "permutations":50}}"#,
+        shifted_observations(80)
+    );
+    let (status, body) = request(address, "POST", "/permutation-test", &kcp);
+    assert_eq!(status, 400);
+    assert!(body.contains("at most 14 are allowed for 80 observations"), "{}", body);
+    let affordable = kcp.replace("\"permutations\":50", "\"permutations\":14");
+    let (status, body) = request(address, "POST", "/permutation-test", &affordable);
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(request(address, "GET", "/detect", "").0, 405);
+    assert_eq!(request(address, "POST", "/segment", "{}").0, 404);
+}
@@ -0,0 +1,196 @@
+use num::Float;
+use algo::non_nan::NonNaN;
+use algo::seasonal::Stl;
+use algo::stats::{median, median_absolute_deviation, student_t_quantile, to_f64s};
+
+use errors::*;
+
+/// Which deviations from the expected value count as anomalies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Direction {
+    Positive,
+    Negative,
+    Both,
+}
+
+// Scales the median absolute deviation to a consistent estimate of the standard deviation of
+// normally distributed data.
+const MAD_SCALE: f64 = 1.4826;
+
+/// Seasonal Hybrid ESD, the anomaly detector from Twitter's AnomalyDetection package.
+///
+/// The seasonal component of a robust, periodic STL decomposition and the median are removed,
+/// and the residuals go through the generalized ESD test with the median and MAD in place of the
+/// mean and standard deviation, flagging at most `max_anomalies` of the observations.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SeasonalHybridESD {
+    period: usize,
+    max_anomalies: f64,
+    alpha: f64,
+    direction: Direction,
+}
+
+impl SeasonalHybridESD {
+    pub fn new(period: usize) -> Self {
+        SeasonalHybridESD {
+            period,
+            max_anomalies: 0.1,
+            alpha: 0.05,
+            direction: Direction::Both,
+        }
+    }
+
+    /// The largest fraction of the observations that may be reported, at most one half.
+    pub fn with_max_anomalies(mut self, max_anomalies: f64) -> Self {
+        self.max_anomalies = max_anomalies;
+        self
+    }
+
+    pub fn with_alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    fn check_parameters(&self) -> Result<()> {
+        if !(self.max_anomalies > 0.0 && self.max_anomalies <= 0.5) {
+            Err(
+                ErrorKind::InvalidParameter(
+                    "max_anomalies".to_string(),
+                    format!("{} is not in (0, 0.5]", self.max_anomalies),
+                ).into(),
+            )
+        } else if !(self.alpha > 0.0 && self.alpha < 1.0) {
+            Err(
+                ErrorKind::InvalidParameter(
+                    "alpha".to_string(),
+                    format!("{} is not in (0, 1)", self.alpha),
+                ).into(),
+            )
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the indices of the anomalous observations in increasing order.
+    pub fn detect<F: Float>(&self, observations: &[NonNaN<F>]) -> Result<Vec<usize>> {
+        self.check_parameters()?;
+        let seasonal = Stl::new(self.period).decompose(observations)?.seasonal;
+        let values = to_f64s(observations);
+        let center = median(values.clone());
+        let residuals: Vec<f64> = values
+            .iter()
+            .zip(seasonal.iter())
+            .map(|(value, seasonal)| value - seasonal - center)
+            .collect();
+        let mut anomalies = self.generalized_esd(&residuals);
+        anomalies.sort();
+        Ok(anomalies)
+    }
+
+    fn deviation(&self, residual: f64, center: f64) -> f64 {
+        match self.direction {
+            Direction::Positive => residual - center,
+            Direction::Negative => center - residual,
+            Direction::Both => (residual - center).abs(),
+        }
+    }
+
+    fn generalized_esd(&self, residuals: &[f64]) -> Vec<usize> {
+        let n = residuals.len();
+        let max_anomalies = (n as f64 * self.max_anomalies).floor() as usize;
+        let tail_probability = match self.direction {
+            Direction::Both => self.alpha / 2.0,
+            Direction::Positive | Direction::Negative => self.alpha,
+        };
+        let mut remaining: Vec<(usize, f64)> = residuals.iter().cloned().enumerate().collect();
+        let mut removed = Vec::with_capacity(max_anomalies);
+        let mut num_anomalies = 0;
+        for i in 1..(max_anomalies + 1) {
+            let values: Vec<f64> = remaining.iter().map(|&(_, value)| value).collect();
+            let center = median(values.clone());
+            let scale = MAD_SCALE * median_absolute_deviation(&values);
+            if scale <= 0.0 {
+                break;
+            }
+            let (position, deviation) = remaining
+                .iter()
+                .enumerate()
+                .map(|(position, &(_, value))| (position, self.deviation(value, center)))
+                .fold((0, f64::NEG_INFINITY), |best, candidate| if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                });
+            removed.push(remaining.remove(position).0);
+            let test_statistic = deviation / scale;
+            let remaining_count = (n - i) as f64;
+            let p = 1.0 - tail_probability / (remaining_count + 1.0);
+            let t = student_t_quantile(p, remaining_count - 1.0);
+            let critical_value = remaining_count * t /
+                ((remaining_count - 1.0 + t * t) * (remaining_count + 1.0)).sqrt();
+            if test_statistic > critical_value {
+                num_anomalies = i;
+            }
+        }
+        removed.truncate(num_anomalies);
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::distributions::{IndependentSample, Normal};
+    use mersenne_twister::MersenneTwister;
+    use std::f64::consts::PI;
+
+    const PERIOD: usize = 24;
+
+    fn daily_cycle_with_spikes(spikes: &[(usize, f64)]) -> Vec<NonNaN<f64>> {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let noise = Normal::new(0.0, 1.0);
+        (0..480)
+            .map(|t| {
+                let cycle = 20.0 * (2.0 * PI * t as f64 / PERIOD as f64).sin();
+                let spike = spikes
+                    .iter()
+                    .find(|&&(index, _)| index == t)
+                    .map(|&(_, size)| size)
+                    .unwrap_or(0.0);
+                NonNaN::new(50.0 + cycle + spike + noise.ind_sample(&mut rng)).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detects_spikes_in_both_directions() {
+        let input = daily_cycle_with_spikes(&[(40, 15.0), (200, -12.0), (333, 10.0)]);
+        let anomalies = SeasonalHybridESD::new(PERIOD).detect(&input).unwrap();
+        assert_eq!(anomalies, vec![40, 200, 333]);
+        let positive = SeasonalHybridESD::new(PERIOD)
+            .with_direction(Direction::Positive)
+            .detect(&input)
+            .unwrap();
+        assert_eq!(positive, vec![40, 333]);
+    }
+
+    #[test]
+    fn seasonal_pattern_alone_is_not_anomalous() {
+        let input = daily_cycle_with_spikes(&[]);
+        let anomalies = SeasonalHybridESD::new(PERIOD).detect(&input).unwrap();
+        assert!(anomalies.is_empty());
+        assert!(SeasonalHybridESD::new(PERIOD)
+            .with_max_anomalies(0.7)
+            .detect(&input)
+            .is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use algo::changepoint::ChangePointDetector;
+use algo::parallelism::Parallelism;
+use algo::permutation_test::{PermutationTestOptions, PermutationTestResult,
+                             permutation_test_with_options};
+use mersenne_twister::MersenneTwister;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use errors::*;
+
+/// A series of observations with the name its results are reported under.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NamedSeries<T> {
+    pub name: String,
+    pub observations: Vec<T>,
+}
+
+impl<T> NamedSeries<T> {
+    pub fn new<S: Into<String>>(name: S, observations: Vec<T>) -> Self {
+        NamedSeries {
+            name: name.into(),
+            observations,
+        }
+    }
+}
+
+/// The outcome of the permutation test on one series of a batch, where a failure only affects
+/// its own series. A detector panicking on a series is reported as `DetectorPanicked`.
+#[derive(Debug)]
+pub struct BatchResult {
+    pub name: String,
+    pub result: Result<PermutationTestResult>,
+}
+
+/// Runs `permutation_test` on every series of the batch, returning the results in the order of
+/// the batch.
+///
+/// With the `parallel` feature, the series are spread across the global rayon thread pool, and the
+/// permutations of each series run on whichever thread picked it up, rather than every series
+/// splitting its permutations across the pool. Each series gets its own generator seeded from
+/// `rng`, so results do not depend on how the work was scheduled.
+pub fn batch_permutation_test<T, B, R>(
+    algorithm: &B,
+    rng: R,
+    num_permutations: usize,
+    batch: &[NamedSeries<T>],
+) -> Vec<BatchResult>
+where
+    T: Clone + Send + Sync,
+    B: ChangePointDetector<T> + Send + Sync,
+    B::Statistic: Send + Sync,
+    R: Rng,
+{
+    batch_permutation_test_with_parallelism(
+        algorithm,
+        rng,
+        num_permutations,
+        batch,
+        &Parallelism::default(),
+    )
+}
+
+/// `batch_permutation_test` with the series spread across the threads given by `parallelism`.
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+pub fn batch_permutation_test_with_parallelism<T, B, R>(
+    algorithm: &B,
+    mut rng: R,
+    num_permutations: usize,
+    batch: &[NamedSeries<T>],
+    parallelism: &Parallelism,
+) -> Vec<BatchResult>
+where
+    T: Clone + Send + Sync,
+    B: ChangePointDetector<T> + Send + Sync,
+    B::Statistic: Send + Sync,
+    R: Rng,
+{
+    let seeds: Vec<u64> = batch.iter().map(|_| rng.gen()).collect();
+    let options = PermutationTestOptions::default().with_parallelism(Parallelism::Sequential);
+    let test_series = |(series, seed): (&NamedSeries<T>, u64)| {
+        let series_rng: MersenneTwister = SeedableRng::from_seed(seed);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            permutation_test_with_options(
+                algorithm,
+                series_rng,
+                num_permutations,
+                &series.observations,
+                &options,
+            )
+        }));
+        BatchResult {
+            name: series.name.clone(),
+            result: result.unwrap_or_else(|panic| {
+                Err(ErrorKind::DetectorPanicked(panic_message(&*panic).to_string()).into())
+            }),
+        }
+    };
+    #[cfg(feature = "parallel")]
+    {
+        if !parallelism.is_sequential() {
+            return parallelism.install(|| batch.par_iter().zip(seeds).map(test_series).collect());
+        }
+    }
+    batch.iter().zip(seeds).map(test_series).collect()
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::best_candidate::BestCandidate;
+    use algo::edm_x::edm_x::EDMX;
+    use algo::non_nan::NonNaN;
+    use generator::normal_series;
+
+    #[test]
+    fn batch_isolates_failures_and_keeps_order() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let batch = vec![
+            NamedSeries::new(
+                "shifted",
+                normal_series(&mut rng, &[(150, 10.0, 5.0), (150, 25.0, 5.0)]),
+            ),
+            NamedSeries::new("too short", normal_series(&mut rng, &[(30, 10.0, 5.0)])),
+            NamedSeries::new("flat", normal_series(&mut rng, &[(300, 10.0, 5.0)])),
+        ];
+        let results = batch_permutation_test(&EDMX::new(20), rng, 19, &batch);
+        let names: Vec<&str> = results.iter().map(|result| result.name.as_str()).collect();
+        assert_eq!(names, vec!["shifted", "too short", "flat"]);
+        let shifted = results[0].result.as_ref().unwrap();
+        assert!(shifted.p_value <= 0.05);
+        assert!((shifted.changepoint_index as i64 - 150).abs() < 20);
+        match *results[1].result.as_ref().unwrap_err().kind() {
+            ErrorKind::NotEnoughValues(30, 41) => {}
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+        assert!(results[2].result.as_ref().unwrap().p_value > 0.05);
+    }
+
+    // EDM-X, except that it panics on series of the given length.
+    struct PanicsOnLength(usize);
+
+    impl ChangePointDetector<NonNaN<f64>> for PanicsOnLength {
+        type Statistic = NonNaN<f64>;
+
+        fn find_candidate(
+            &self,
+            observations: &[NonNaN<f64>],
+        ) -> Result<BestCandidate<NonNaN<f64>>> {
+            if observations.len() == self.0 {
+                panic!("{} observations", observations.len());
+            }
+            EDMX::new(10).find_candidate(observations)
+        }
+    }
+
+    #[test]
+    fn batch_reports_a_panicking_series_as_its_error() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let batch = vec![
+            NamedSeries::new("first", normal_series(&mut rng, &[(60, 10.0, 5.0)])),
+            NamedSeries::new("panics", normal_series(&mut rng, &[(77, 10.0, 5.0)])),
+            NamedSeries::new("last", normal_series(&mut rng, &[(60, 10.0, 5.0)])),
+        ];
+        let results = batch_permutation_test(&PanicsOnLength(77), rng, 9, &batch);
+        assert!(results[0].result.is_ok() && results[2].result.is_ok());
+        match *results[1].result.as_ref().unwrap_err().kind() {
+            ErrorKind::DetectorPanicked(ref message) => assert_eq!(message, "77 observations"),
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn batch_results_do_not_depend_on_scheduling() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let batch: Vec<_> = (0..8)
+            .map(|i| {
+                let observations = normal_series(&mut rng, &[(60, 10.0, 5.0), (60, 12.0, 5.0)]);
+                NamedSeries::new(i.to_string(), observations)
+            })
+            .collect();
+        let p_values = || -> Vec<f64> {
+            let rng: MersenneTwister = SeedableRng::from_seed(0x5678);
+            batch_permutation_test(&EDMX::new(10), rng, 9, &batch)
+                .into_iter()
+                .map(|result| result.result.unwrap().p_value)
+                .collect()
+        };
+        assert_eq!(p_values(), p_values());
+        let rng: MersenneTwister = SeedableRng::from_seed(0x5678);
+        let sequential: Vec<f64> = batch_permutation_test_with_parallelism(
+            &EDMX::new(10),
+            rng,
+            9,
+            &batch,
+            &Parallelism::Sequential,
+        ).into_iter()
+            .map(|result| result.result.unwrap().p_value)
+            .collect();
+        assert_eq!(p_values(), sequential);
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Stops a running permutation test or detector, either on request or once a deadline passes.
+///
+/// Clones share the same flag, so a clone handed to the workers sees `cancel` called on the
+/// original.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Also counts as cancelled from `deadline` on.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Also counts as cancelled once `timeout` has elapsed from now.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) ||
+            self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_cancellation_and_deadlines_expire() {
+        let token = CancellationToken::new();
+        let worker = token.clone();
+        assert!(!worker.is_cancelled());
+        token.cancel();
+        assert!(worker.is_cancelled());
+        assert!(!CancellationToken::new().with_timeout(Duration::from_secs(3600)).is_cancelled());
+        assert!(CancellationToken::new().with_deadline(Instant::now()).is_cancelled());
+    }
+}
@@ -2,6 +2,99 @@ use algo::best_candidate::BestCandidate;
 
 use errors::*;
 
-pub trait ChangePointDetector<T: Ord> {
-    fn find_candidate(&self, observations: &[T]) -> Result<BestCandidate<T>>;
+pub trait ChangePointDetector<T> {
+    type Statistic: Ord;
+
+    fn find_candidate(&self, observations: &[T]) -> Result<BestCandidate<Self::Statistic>>;
+
+    /// The fewest observations `find_candidate` accepts.
+    fn min_observations(&self) -> usize {
+        2
+    }
+
+    /// A short name for the method, such as `edmx`, which defaults to the name of the type.
+    fn name(&self) -> String {
+        ::std::any::type_name::<Self>().to_string()
+    }
+
+    /// The parameters the detector was configured with, as pairs of name and value.
+    fn parameters(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// The name followed by the parameters, such as `edmx(delta=30)`.
+    fn describe(&self) -> String {
+        let parameters: Vec<String> = self.parameters()
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        format!("{}({})", self.name(), parameters.join(", "))
+    }
+
+    /// The statistic at every location considered, in order of location, for detectors scoring
+    /// each location on its own. `find_candidate` reports the maximum of the curve.
+    fn statistic_curve(
+        &self,
+        _observations: &[T],
+    ) -> Result<Option<Vec<BestCandidate<Self::Statistic>>>> {
+        Ok(None)
+    }
+
+    /// The p-value of `candidate` under the asymptotic null distribution of the statistic, for
+    /// detectors where one is known.
+    fn asymptotic_p_value(
+        &self,
+        _observations: &[T],
+        _candidate: &BestCandidate<Self::Statistic>,
+    ) -> Result<f64> {
+        Err(ErrorKind::NoAsymptoticDistribution.into())
+    }
+}
+
+impl<T, D: ChangePointDetector<T> + ?Sized> ChangePointDetector<T> for Box<D> {
+    type Statistic = D::Statistic;
+
+    fn find_candidate(&self, observations: &[T]) -> Result<BestCandidate<Self::Statistic>> {
+        (**self).find_candidate(observations)
+    }
+
+    fn min_observations(&self) -> usize {
+        (**self).min_observations()
+    }
+
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn parameters(&self) -> Vec<(String, String)> {
+        (**self).parameters()
+    }
+
+    fn describe(&self) -> String {
+        (**self).describe()
+    }
+
+    fn statistic_curve(
+        &self,
+        observations: &[T],
+    ) -> Result<Option<Vec<BestCandidate<Self::Statistic>>>> {
+        (**self).statistic_curve(observations)
+    }
+
+    fn asymptotic_p_value(
+        &self,
+        observations: &[T],
+        candidate: &BestCandidate<Self::Statistic>,
+    ) -> Result<f64> {
+        (**self).asymptotic_p_value(observations, candidate)
+    }
+}
+
+// Fails with `NotEnoughValues` when there are fewer than `min_observations`.
+pub(crate) fn check_observations(count: usize, min_observations: usize) -> Result<()> {
+    if count < min_observations {
+        Err(ErrorKind::NotEnoughValues(count, min_observations).into())
+    } else {
+        Ok(())
+    }
 }
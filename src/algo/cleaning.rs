@@ -0,0 +1,183 @@
+use num::Float;
+use algo::non_nan::NonNaN;
+use algo::stats::{from_f64, median, to_f64};
+
+use errors::*;
+
+/// How NaN and infinite values are handled when turning raw floats into `NonNaN`s. Whatever the
+/// policy, a missing value with no finite value to be filled from is dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MissingValuePolicy {
+    Drop,
+    /// Repeat the last finite value before the missing one.
+    ForwardFill,
+    /// Interpolate linearly between the nearest finite values, repeating the nearest finite
+    /// value at either end of the series.
+    LinearInterpolate,
+    /// Replace with the median of all finite values.
+    MedianImpute,
+    /// Fail with the index of the first missing value.
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RepairAction<F: Float> {
+    Dropped,
+    Imputed(NonNaN<F>),
+}
+
+/// A missing value at `index` in the original series, and what was done about it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Repair<F: Float> {
+    pub index: usize,
+    pub original: F,
+    pub action: RepairAction<F>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cleaned<F: Float> {
+    pub values: Vec<NonNaN<F>>,
+    /// `values[i]` stands in for the original value at `original_indices[i]`.
+    pub original_indices: Vec<usize>,
+    pub repairs: Vec<Repair<F>>,
+}
+
+impl<F: Float> Cleaned<F> {
+    pub fn original_index(&self, index: usize) -> usize {
+        self.original_indices[index]
+    }
+}
+
+fn interpolate<F: Float>(finite: &[(usize, NonNaN<F>)], index: usize) -> Option<NonNaN<F>> {
+    let after = finite.iter().position(|&(position, _)| position > index);
+    match after {
+        Some(0) => finite.first().map(|&(_, value)| value),
+        Some(after) => {
+            let (before_index, before_value) = finite[after - 1];
+            let (after_index, after_value) = finite[after];
+            let fraction = (index - before_index) as f64 / (after_index - before_index) as f64;
+            let before_value = to_f64(&before_value);
+            Some(from_f64(
+                before_value + (to_f64(&after_value) - before_value) * fraction,
+            ))
+        }
+        None => finite.last().map(|&(_, value)| value),
+    }
+}
+
+/// Converts raw floats to `NonNaN`s, handling NaN and infinite values with the given policy and
+/// keeping track of every value that was dropped or imputed.
+pub fn clean<F: Float>(float_slice: &[F], policy: MissingValuePolicy) -> Result<Cleaned<F>> {
+    let finite: Vec<(usize, NonNaN<F>)> = float_slice
+        .iter()
+        .enumerate()
+        .filter_map(|(index, value)| NonNaN::new(*value).map(|value| (index, value)))
+        .collect();
+    let median_value = if finite.is_empty() {
+        None
+    } else {
+        Some(from_f64(
+            median(finite.iter().map(|&(_, value)| to_f64(&value)).collect()),
+        ))
+    };
+    let mut cleaned = Cleaned {
+        values: Vec::with_capacity(float_slice.len()),
+        original_indices: Vec::with_capacity(float_slice.len()),
+        repairs: Vec::new(),
+    };
+    for (index, value) in float_slice.iter().enumerate() {
+        if let Some(non_nan) = NonNaN::new(*value) {
+            cleaned.values.push(non_nan);
+            cleaned.original_indices.push(index);
+            continue;
+        }
+        let imputed = match policy {
+            MissingValuePolicy::Error => {
+                let display_value = value.to_f64().unwrap_or(f64::NAN);
+                return Err(
+                    ErrorKind::NonFiniteValueAt(index, display_value.to_string()).into(),
+                );
+            }
+            MissingValuePolicy::Drop => None,
+            MissingValuePolicy::ForwardFill => cleaned.values.last().cloned(),
+            MissingValuePolicy::LinearInterpolate => interpolate(&finite, index),
+            MissingValuePolicy::MedianImpute => median_value,
+        };
+        let action = match imputed {
+            Some(imputed) => {
+                cleaned.values.push(imputed);
+                cleaned.original_indices.push(index);
+                RepairAction::Imputed(imputed)
+            }
+            None => RepairAction::Dropped,
+        };
+        cleaned.repairs.push(Repair {
+            index,
+            original: *value,
+            action,
+        });
+    }
+    Ok(cleaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW: [f64; 7] = [f64::NAN, 1.0, f64::INFINITY, 3.0, f64::NAN, f64::NAN, 6.0];
+
+    fn cleaned_values(policy: MissingValuePolicy) -> (Vec<f64>, Vec<usize>) {
+        let cleaned = clean(&RAW, policy).unwrap();
+        (
+            cleaned.values.iter().map(|value| value.value()).collect(),
+            cleaned.original_indices,
+        )
+    }
+
+    #[test]
+    fn policies_repair_missing_values() {
+        assert_eq!(
+            cleaned_values(MissingValuePolicy::Drop),
+            (vec![1.0, 3.0, 6.0], vec![1, 3, 6])
+        );
+        assert_eq!(
+            cleaned_values(MissingValuePolicy::ForwardFill),
+            (vec![1.0, 1.0, 3.0, 3.0, 3.0, 6.0], vec![1, 2, 3, 4, 5, 6])
+        );
+        assert_eq!(
+            cleaned_values(MissingValuePolicy::LinearInterpolate),
+            (
+                vec![1.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+                vec![0, 1, 2, 3, 4, 5, 6],
+            )
+        );
+        assert_eq!(
+            cleaned_values(MissingValuePolicy::MedianImpute).0,
+            vec![3.0, 1.0, 3.0, 3.0, 3.0, 3.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn repairs_are_reported() {
+        let cleaned = clean(&RAW, MissingValuePolicy::ForwardFill).unwrap();
+        assert_eq!(cleaned.repairs.len(), 4);
+        assert_eq!(cleaned.repairs[0].index, 0);
+        assert_eq!(cleaned.repairs[0].action, RepairAction::Dropped);
+        assert_eq!(cleaned.repairs[1].index, 2);
+        assert_eq!(
+            cleaned.repairs[1].action,
+            RepairAction::Imputed(NonNaN::new(1.0).unwrap())
+        );
+        assert_eq!(cleaned.original_index(2), 3);
+        match *clean(&RAW, MissingValuePolicy::Error).unwrap_err().kind() {
+            ErrorKind::NonFiniteValueAt(0, ref value) => assert_eq!(value, "NaN"),
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+    }
+}
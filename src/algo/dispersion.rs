@@ -0,0 +1,289 @@
+use algo::best_candidate::BestCandidate;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use algo::changepoint::{ChangePointDetector, check_observations};
+use algo::edm_x::edm_x::HeapNum;
+
+use errors::*;
+
+/// The robust measure of spread compared on either side of a candidate split.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Dispersion {
+    MedianAbsoluteDeviation,
+    InterquartileRange,
+}
+
+fn two<T: HeapNum>() -> T {
+    T::one() + T::one()
+}
+
+pub fn median_of_sorted<T: HeapNum>(sorted: &[T]) -> T {
+    let len = sorted.len();
+    if len % 2 == 1 {
+        sorted[len / 2].clone()
+    } else {
+        (sorted[len / 2 - 1].clone() + sorted[len / 2].clone()) / two()
+    }
+}
+
+fn interquartile_range<T: HeapNum>(sorted: &[T]) -> T {
+    let len = sorted.len();
+    let lower_quartile = median_of_sorted(&sorted[..len / 2]);
+    let upper_quartile = median_of_sorted(&sorted[len - len / 2..]);
+    upper_quartile - lower_quartile
+}
+
+// The deviations below and above the median are each already sorted when read outwards from the
+// median, so the median deviation is found by merging the two runs instead of sorting them.
+pub fn median_absolute_deviation<T: HeapNum>(sorted: &[T]) -> T {
+    let len = sorted.len();
+    let median = median_of_sorted(sorted);
+    let split = sorted.iter().take_while(|value| **value <= median).count();
+    let mut below = sorted[..split].iter().rev().map(|value| median.clone() - value.clone());
+    let mut above = sorted[split..].iter().map(|value| value.clone() - median.clone());
+    let mut next_below = below.next();
+    let mut next_above = above.next();
+    let mut previous = T::zero();
+    let mut current = T::zero();
+    for _ in 0..(len / 2 + 1) {
+        let take_below = match (&next_below, &next_above) {
+            (Some(low), Some(high)) => low <= high,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        let next = if take_below {
+            let next = next_below.take().expect("take_below is only true when Some");
+            next_below = below.next();
+            next
+        } else {
+            let next = next_above.take().expect(
+                "The loop never runs past the number of deviations available",
+            );
+            next_above = above.next();
+            next
+        };
+        previous = current;
+        current = next;
+    }
+    if len % 2 == 1 {
+        current
+    } else {
+        (previous + current) / two()
+    }
+}
+
+fn dispersion_of_sorted<T: HeapNum>(sorted: &[T], dispersion: Dispersion) -> T {
+    if sorted.len() < 2 {
+        return T::zero();
+    }
+    match dispersion {
+        Dispersion::MedianAbsoluteDeviation => median_absolute_deviation(sorted),
+        Dispersion::InterquartileRange => interquartile_range(sorted),
+    }
+}
+
+fn insert_sorted<T: HeapNum>(sorted: &mut Vec<T>, value: T) {
+    let position = match sorted.binary_search(&value) {
+        Ok(position) | Err(position) => position,
+    };
+    sorted.insert(position, value);
+}
+
+fn remove_sorted<T: HeapNum>(sorted: &mut Vec<T>, value: &T) {
+    let position = sorted.binary_search(value).expect(
+        "Only values previously inserted into the window are removed from it",
+    );
+    sorted.remove(position);
+}
+
+// The score of every split location, in order of location.
+fn edm_scale_curve<T>(z: &[T], delta: usize, dispersion: Dispersion) -> Vec<BestCandidate<T>>
+where
+    T: HeapNum + From<f64>,
+{
+    let n = z.len();
+    let mut left: Vec<T> = z[..delta].to_vec();
+    left.sort();
+    let mut right: Vec<T> = z[delta..].to_vec();
+    right.sort();
+    let n_float = n as f64;
+    (delta..(n - delta + 1))
+        .map(|i| {
+            if i > delta {
+                let moved = z[i - 1].clone();
+                remove_sorted(&mut right, &moved);
+                insert_sorted(&mut left, moved);
+            }
+            let dispersion_diff = dispersion_of_sorted(&left, dispersion) -
+                dispersion_of_sorted(&right, dispersion);
+            let dispersion_diff_squared = dispersion_diff.clone() * dispersion_diff;
+            let i_float = i as f64;
+            let stat_weight = (i_float * (n_float - i_float)) / n_float;
+            BestCandidate {
+                statistic: T::from(stat_weight) * dispersion_diff_squared,
+                location: i,
+            }
+        })
+        .collect()
+}
+
+/// A sibling of `EDMX` which looks for a change in spread rather than in central tendency.
+///
+/// Every split leaving at least `delta` observations on each side is scored by the weighted
+/// squared difference of the chosen `Dispersion` on either side, and the reported location is the
+/// index of the first observation after the change.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedEDMScale"))]
+pub struct EDMScale {
+    delta: usize,
+    dispersion: Dispersion,
+}
+
+// Deserialized configurations are checked before they can be run.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedEDMScale {
+    delta: usize,
+    dispersion: Dispersion,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedEDMScale> for EDMScale {
+    type Error = Error;
+
+    fn try_from(unchecked: UncheckedEDMScale) -> Result<Self> {
+        let detector = EDMScale::new(unchecked.delta, unchecked.dispersion);
+        detector.check_parameters()?;
+        Ok(detector)
+    }
+}
+
+impl EDMScale {
+    pub fn new(delta: usize, dispersion: Dispersion) -> Self {
+        EDMScale { delta, dispersion }
+    }
+}
+
+impl EDMScale {
+    // The spread of an empty side is undefined, so `delta` is a parameter error rather than a
+    // reason to ask for more observations.
+    pub(crate) fn check_parameters(&self) -> Result<()> {
+        if self.delta == 0 {
+            return Err(
+                ErrorKind::InvalidParameter("delta".to_string(), "must be at least 1".to_string())
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+
+    fn checked_curve<T>(&self, observations: &[T]) -> Result<Vec<BestCandidate<T>>>
+    where
+        T: HeapNum + From<f64>,
+    {
+        self.check_parameters()?;
+        check_observations(observations.len(), self.delta * 2)?;
+        Ok(edm_scale_curve(observations, self.delta, self.dispersion))
+    }
+}
+
+impl<T: HeapNum + From<f64>> ChangePointDetector<T> for EDMScale {
+    type Statistic = T;
+
+    fn find_candidate(&self, observations: &[T]) -> Result<BestCandidate<T>> {
+        Ok(self.checked_curve(observations)?.into_iter().max().expect(
+            "The curve is never empty because observations has at least 2 * delta values",
+        ))
+    }
+
+    fn min_observations(&self) -> usize {
+        self.delta * 2
+    }
+
+    fn name(&self) -> String {
+        match self.dispersion {
+            Dispersion::MedianAbsoluteDeviation => "edm-mad",
+            Dispersion::InterquartileRange => "edm-iqr",
+        }.to_string()
+    }
+
+    fn parameters(&self) -> Vec<(String, String)> {
+        vec![("delta".to_string(), self.delta.to_string())]
+    }
+
+    fn statistic_curve(&self, observations: &[T]) -> Result<Option<Vec<BestCandidate<T>>>> {
+        self.checked_curve(observations).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::non_nan::NonNaN;
+    use rand::SeedableRng;
+    use mersenne_twister::MersenneTwister;
+    use generator::{Segment, SeriesGenerator};
+    use num::abs;
+
+    fn sorted_non_nans(values: &[f64]) -> Vec<NonNaN<f64>> {
+        let mut result: Vec<NonNaN<f64>> =
+            values.iter().map(|v| NonNaN::new(*v).unwrap()).collect();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn dispersion_measures_match_direct_computation() {
+        let odd = sorted_non_nans(&[1.0, 1.0, 2.0, 2.0, 4.0, 6.0, 9.0]);
+        assert_eq!(median_absolute_deviation(&odd).value(), 1.0);
+        assert_eq!(interquartile_range(&odd).value(), 5.0);
+        let even = sorted_non_nans(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(median_absolute_deviation(&even).value(), 1.5);
+        assert_eq!(interquartile_range(&even).value(), 3.0);
+    }
+
+    #[test]
+    fn edm_scale_finds_change_in_spread() {
+        let before_change_count = 300;
+        let after_change_count = 300;
+        let delta = 30;
+        let tolerance = 50;
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let input = SeriesGenerator::new(vec![
+            Segment::new(before_change_count, 10.0),
+            Segment::new(after_change_count, 10.0).with_variance(36.0),
+        ]).generate(&mut rng)
+            .unwrap()
+            .values;
+        for dispersion in &[
+            Dispersion::MedianAbsoluteDeviation,
+            Dispersion::InterquartileRange,
+        ]
+        {
+            let best_candidate = EDMScale::new(delta, *dispersion)
+                .find_candidate(&input)
+                .unwrap();
+            let abs_loc_diff = abs(best_candidate.location as i64 - before_change_count as i64);
+            assert!(abs_loc_diff < tolerance);
+        }
+    }
+
+    #[test]
+    fn edm_scale_rejects_a_delta_of_zero_as_a_parameter_error() {
+        let input: Vec<NonNaN<f64>> = (0..20).map(|i| NonNaN::new(i as f64).unwrap()).collect();
+        let detector = EDMScale::new(0, Dispersion::MedianAbsoluteDeviation);
+        match *detector.find_candidate(&input).unwrap_err().kind() {
+            ErrorKind::InvalidParameter(ref name, _) => assert_eq!(name, "delta"),
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+        let detector = EDMScale::new(15, Dispersion::InterquartileRange);
+        match *detector.find_candidate(&input).unwrap_err().kind() {
+            ErrorKind::NotEnoughValues(20, 30) => {}
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+        assert_eq!(ChangePointDetector::<NonNaN<f64>>::min_observations(&detector), 30);
+    }
+}
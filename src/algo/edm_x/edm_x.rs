@@ -1,9 +1,12 @@
 use num::{One, Num};
 use std::collections::BinaryHeap;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
 use std::ops::{Index, RangeFrom};
 use algo::edm_x::heap::{MaxHeap, MaxHeapItem, MinHeap, MinHeapItem};
 use algo::best_candidate::BestCandidate;
-use algo::changepoint::ChangePointDetector;
+use algo::cancellation::CancellationToken;
+use algo::changepoint::{ChangePointDetector, check_observations};
 
 use errors::*;
 
@@ -36,13 +39,13 @@ impl<T: HeapNum> Heaps<T> {
         let min_heap: MinHeap<T> = BinaryHeap::new();
         let max_heap: MaxHeap<T> = BinaryHeap::new();
         Heaps {
-            min_heap: min_heap,
-            max_heap: max_heap,
+            min_heap,
+            max_heap,
             heap_size_info: HeapSizeInfo::Empty,
         }
     }
 
-    fn push_to_heap(&mut self, push_item: HeapItem<T>) -> () {
+    fn push_to_heap(&mut self, push_item: HeapItem<T>) {
         self.heap_size_info = match (&self.heap_size_info, push_item) {
             (&HeapSizeInfo::Empty, HeapItem::FirstPush(item)) => {
                 self.min_heap.push(MinHeapItem(item.clone()));
@@ -91,10 +94,10 @@ impl<T: HeapNum> Heaps<T> {
         };
     }
 
-    fn add_to_heaps(&mut self, value: T) -> () {
-        let heap_item_to_push = match &self.heap_size_info {
-            &HeapSizeInfo::Empty => HeapItem::FirstPush(value),
-            _ if &value <= &self.min_heap.peek()
+    fn add_to_heaps(&mut self, value: T) {
+        let heap_item_to_push = match self.heap_size_info {
+            HeapSizeInfo::Empty => HeapItem::FirstPush(value),
+            _ if value <= self.min_heap.peek()
                 .expect("Must be Some -- previous pattern match with Empty ensures a value is present in both heaps.").0 =>
                 HeapItem::MaxHeap(MaxHeapItem(value)),
             _ => HeapItem::MinHeap(MinHeapItem(value)),
@@ -103,27 +106,27 @@ impl<T: HeapNum> Heaps<T> {
     }
 
     fn get_median(&self) -> T {
-        match &self.heap_size_info {
-            &HeapSizeInfo::Empty => {
+        match self.heap_size_info {
+            HeapSizeInfo::Empty => {
                 unimplemented!(
                     "get_median is never called in the EDM-X algorithm before a value is pushed to the heaps."
                 )
             }
-            &HeapSizeInfo::MinHeapBigger => {
+            HeapSizeInfo::MinHeapBigger => {
                 self.min_heap
                     .peek()
                     .expect("Item must be Some because HeapSizeInfo is not Empty")
                     .0
                     .clone()
             }
-            &HeapSizeInfo::MaxHeapBigger => {
+            HeapSizeInfo::MaxHeapBigger => {
                 self.max_heap
                     .peek()
                     .expect("Item must be Some because HeapSizeInfo is not Empty")
                     .0
                     .clone()
             }
-            &HeapSizeInfo::EqualSizes => {
+            HeapSizeInfo::EqualSizes => {
                 let min_heap_value = self.min_heap
                     .peek()
                     .expect("Item must be Some because HeapSizeInfo is not Empty")
@@ -172,12 +175,25 @@ where
                 Some(Some(candidate))
             }
         })
-        .filter_map(|result| result)
+        .flatten()
         .max()
-        .expect("filter_map ensures result is Some")
+        .expect("flatten ensures result is Some")
 }
 
-fn edm_x<T>(z: &[T], delta: usize) -> BestCandidate<T>
+// The best candidate for every split location, in order of location.
+pub fn edm_x_curve<T>(z: &[T], delta: usize) -> Vec<BestCandidate<T>>
+where
+    T: HeapNum + From<f64>,
+{
+    cancellable_edm_x_curve(z, delta, None).expect("Only cancellation fails the curve")
+}
+
+// `edm_x_curve`, checking `cancellation` before every split location.
+fn cancellable_edm_x_curve<T>(
+    z: &[T],
+    delta: usize,
+    cancellation: Option<&CancellationToken>,
+) -> Result<Vec<BestCandidate<T>>>
 where
     T: HeapNum + From<f64>,
 {
@@ -189,6 +205,8 @@ where
             left_heaps.add_to_heaps(next_item.clone());
             if i < delta {
                 Some(None)
+            } else if cancellation.is_some_and(CancellationToken::is_cancelled) {
+                Some(Some(Err(ErrorKind::Cancelled.into())))
             } else {
                 let left_median = left_heaps.get_median();
                 let inner_best_candidate = inner_edm_x_loop(
@@ -197,34 +215,108 @@ where
                     z.index(RangeFrom { start: i }).iter(),
                     i,
                 );
-                Some(Some(inner_best_candidate))
+                Some(Some(Ok(inner_best_candidate)))
             }
         })
-        .filter_map(|result| result)
+        .flatten()
+        .collect()
+}
+
+fn edm_x<T>(
+    z: &[T],
+    delta: usize,
+    cancellation: Option<&CancellationToken>,
+) -> Result<BestCandidate<T>>
+where
+    T: HeapNum + From<f64>,
+{
+    Ok(cancellable_edm_x_curve(z, delta, cancellation)?
+        .into_iter()
         .max()
-        .expect("filter_map ensures result is Some")
+        .expect(
+            "The curve is never empty for collections with more than twice delta elements",
+        ))
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedEDMX"))]
 pub struct EDMX {
     delta: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cancellation: Option<CancellationToken>,
+}
+
+// Deserialized configurations are checked before they can be run.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedEDMX {
+    delta: usize,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedEDMX> for EDMX {
+    type Error = Error;
+
+    fn try_from(unchecked: UncheckedEDMX) -> Result<Self> {
+        let detector = EDMX::new(unchecked.delta);
+        detector.check_parameters()?;
+        Ok(detector)
+    }
 }
 
 impl EDMX {
     pub fn new(delta: usize) -> Self {
-        EDMX { delta: delta }
+        EDMX {
+            delta,
+            cancellation: None,
+        }
+    }
+
+    /// Stops `find_candidate` with a `Cancelled` error once `cancellation` is cancelled.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    // The medians on either side of a split are undefined for a delta of zero.
+    fn check_parameters(&self) -> Result<()> {
+        if self.delta == 0 {
+            return Err(
+                ErrorKind::InvalidParameter("delta".to_string(), "must be at least 1".to_string())
+                    .into(),
+            );
+        }
+        Ok(())
     }
 }
 
 impl<T: HeapNum + From<f64>> ChangePointDetector<T> for EDMX {
+    type Statistic = T;
+
     fn find_candidate(&self, observations: &[T]) -> Result<BestCandidate<T>> {
-        if observations.len() < self.delta * 2 {
-            Err(
-                ErrorKind::NotEnoughValues(observations.len(), self.delta).into(),
-            )
-        } else {
-            Ok(edm_x(observations, self.delta))
-        }
+        self.check_parameters()?;
+        check_observations(observations.len(), self.delta * 2 + 1)?;
+        edm_x(observations, self.delta, self.cancellation.as_ref())
+    }
+
+    // The median of the right segment needs delta observations after the split location.
+    fn min_observations(&self) -> usize {
+        self.delta * 2 + 1
+    }
+
+    fn name(&self) -> String {
+        "edmx".to_string()
+    }
+
+    fn parameters(&self) -> Vec<(String, String)> {
+        vec![("delta".to_string(), self.delta.to_string())]
+    }
+
+    fn statistic_curve(&self, observations: &[T]) -> Result<Option<Vec<BestCandidate<T>>>> {
+        self.check_parameters()?;
+        check_observations(observations.len(), self.delta * 2 + 1)?;
+        cancellable_edm_x_curve(observations, self.delta, self.cancellation.as_ref()).map(Some)
     }
 }
 
@@ -233,16 +325,16 @@ mod tests {
     use super::*;
     use algo::non_nan::NonNaN;
     use rand::SeedableRng;
-    use rand::distributions::{IndependentSample, Normal};
     use mersenne_twister::MersenneTwister;
+    use generator::{Segment, SeriesGenerator};
     use num::abs;
 
     #[test]
     fn heaps_find_the_median() {
         let initial_number: NonNaN<f32> = NonNaN::new(1.0).unwrap();
         let mut heaps: Heaps<NonNaN<f32>> = Heaps::new();
-        heaps.add_to_heaps(initial_number.clone());
-        assert_eq!(heaps.get_median(), initial_number.clone());
+        heaps.add_to_heaps(initial_number);
+        assert_eq!(heaps.get_median(), initial_number);
         heaps.add_to_heaps(NonNaN::new(2.0).unwrap());
         heaps.add_to_heaps(NonNaN::new(3.0).unwrap());
         heaps.add_to_heaps(NonNaN::new(4.0).unwrap());
@@ -260,19 +352,58 @@ mod tests {
         let delta = 10;
         let tolerance = 50;
         let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
-        let mut input: Vec<NonNaN<f64>> = Vec::new();
-        let before_change_dist = Normal::new(10.0, 5.0);
-        for _ in 0..before_change_count {
-            input.push(
-                NonNaN::new(before_change_dist.ind_sample(&mut rng)).unwrap(),
-            );
+        let series = SeriesGenerator::new(vec![
+            Segment::new(before_change_count, 10.0).with_variance(25.0),
+            Segment::new(after_change_count, 30.0).with_variance(25.0),
+        ]).generate(&mut rng)
+            .unwrap();
+        let best_candidate = edm_x(&series.values, delta, None).unwrap();
+        let abs_loc_diff = abs(best_candidate.location as i64 - series.changepoints[0] as i64);
+        assert!(abs_loc_diff < tolerance);
+    }
+
+    #[test]
+    fn cancelled_edm_x_stops_with_an_error() {
+        let input: Vec<NonNaN<f64>> = (0..100).map(|i| NonNaN::new(i as f64).unwrap()).collect();
+        let cancellation = CancellationToken::new();
+        let detector = EDMX::new(10).with_cancellation(cancellation.clone());
+        assert!(detector.find_candidate(&input).is_ok());
+        cancellation.cancel();
+        match *detector.find_candidate(&input).unwrap_err().kind() {
+            ErrorKind::Cancelled => {}
+            ref other => panic!("Unexpected error {:?}", other),
         }
-        let after_change_dist = Normal::new(30.0, 5.0);
-        for _ in 0..after_change_count {
-            input.push(NonNaN::new(after_change_dist.ind_sample(&mut rng)).unwrap());
+    }
+
+    #[test]
+    fn edm_x_needs_more_than_twice_delta_observations() {
+        let input: Vec<NonNaN<f64>> = (0..100).map(|i| NonNaN::new(i as f64).unwrap()).collect();
+        match *EDMX::new(50).find_candidate(&input).unwrap_err().kind() {
+            ErrorKind::NotEnoughValues(100, 101) => {}
+            ref other => panic!("Unexpected error {:?}", other),
         }
-        let best_candidate = edm_x(&input, delta);
-        let abs_loc_diff = abs(best_candidate.location as i64 - before_change_count as i64);
-        assert!(abs_loc_diff < tolerance);
+        assert!(EDMX::new(49).find_candidate(&input).is_ok());
+    }
+
+    #[test]
+    fn edm_x_rejects_a_delta_of_zero() {
+        let input: Vec<NonNaN<f64>> = (0..5).map(|i| NonNaN::new(i as f64).unwrap()).collect();
+        match *EDMX::new(0).find_candidate(&input).unwrap_err().kind() {
+            ErrorKind::InvalidParameter(ref name, _) => assert_eq!(name, "delta"),
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn edm_x_describes_itself_and_its_curve() {
+        let input: Vec<NonNaN<f64>> = (0..60)
+            .map(|i| NonNaN::new(if i < 30 { 0.0 } else { 10.0 } + (i % 3) as f64).unwrap())
+            .collect();
+        let detector = EDMX::new(10);
+        assert_eq!(ChangePointDetector::<NonNaN<f64>>::describe(&detector), "edmx(delta=10)");
+        assert_eq!(ChangePointDetector::<NonNaN<f64>>::min_observations(&detector), 21);
+        let curve = detector.statistic_curve(&input).unwrap().unwrap();
+        assert_eq!(curve.first().unwrap().location, 10);
+        assert_eq!(curve.into_iter().max(), Some(detector.find_candidate(&input).unwrap()));
     }
 }
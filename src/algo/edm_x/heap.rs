@@ -10,14 +10,14 @@ fn min_heap_cmp<T>(this: &MinHeapItem<T>, other: &MinHeapItem<T>) -> Ordering
 where
     T: PartialEq + Eq + PartialOrd + Ord,
 {
-    let &MinHeapItem(ref this) = this;
-    let &MinHeapItem(ref other) = other;
+    let MinHeapItem(this) = this;
+    let MinHeapItem(other) = other;
     this.cmp(other).reverse()
 }
 
 impl<T: Ord> PartialOrd for MinHeapItem<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(min_heap_cmp(self, other))
+        Some(self.cmp(other))
     }
 }
 
@@ -31,14 +31,14 @@ fn max_heap_cmp<T>(this: &MaxHeapItem<T>, other: &MaxHeapItem<T>) -> Ordering
 where
     T: PartialEq + Eq + PartialOrd + Ord,
 {
-    let &MaxHeapItem(ref this) = this;
-    let &MaxHeapItem(ref other) = other;
+    let MaxHeapItem(this) = this;
+    let MaxHeapItem(other) = other;
     this.cmp(other)
 }
 
 impl<T: Ord> PartialOrd for MaxHeapItem<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(max_heap_cmp(self, other))
+        Some(self.cmp(other))
     }
 }
 
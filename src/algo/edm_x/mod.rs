@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 pub mod edm_x;
 pub mod heap;
@@ -0,0 +1,800 @@
+use num::Float;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use algo::best_candidate::BestCandidate;
+use algo::changepoint::{ChangePointDetector, check_observations};
+use algo::non_nan::NonNaN;
+use algo::multivariate::check_dimensions;
+use algo::stats::{from_f64, median, standardized_bridge_exceedance, to_f64, to_f64s};
+
+use errors::*;
+
+/// The kernel used to embed observations before comparing segments.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Kernel {
+    /// Gaussian RBF kernel with the given bandwidth, or a bandwidth picked by the median
+    /// heuristic (the median pairwise distance between observations) when `None`.
+    Gaussian(Option<f64>),
+    /// Plain inner product, which makes KCP a least-squares detector of changes in the mean.
+    Linear,
+}
+
+/// How the number of changepoints reported by `KCP::segment` is chosen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ModelSelection {
+    /// Calibrate the penalty constant from the data with the slope heuristic.
+    SlopeHeuristic,
+    /// Use the given constant in front of the penalty shape.
+    Penalty(f64),
+    /// Always report exactly this many changepoints (or as many as fit in the series).
+    Fixed(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KernelSegmentation {
+    /// Indices of the first observation of every segment but the first.
+    pub changepoints: Vec<usize>,
+    /// Within-segment kernel cost of the selected segmentation.
+    pub cost: f64,
+    /// Optimal cost for every number of changepoints from zero up to the maximum considered.
+    pub costs: Vec<f64>,
+}
+
+fn squared_distance(left: &[f64], right: &[f64]) -> f64 {
+    left.iter()
+        .zip(right.iter())
+        .map(|(l, r)| (l - r) * (l - r))
+        .sum()
+}
+
+const MEDIAN_HEURISTIC_SAMPLE: usize = 500;
+
+fn median_heuristic(points: &[Vec<f64>]) -> f64 {
+    let step = (points.len() / MEDIAN_HEURISTIC_SAMPLE).max(1);
+    let sample: Vec<&Vec<f64>> = points.iter().step_by(step).collect();
+    let mut distances = Vec::with_capacity(sample.len() * sample.len() / 2);
+    for (i, left) in sample.iter().enumerate() {
+        for right in sample.iter().skip(i + 1) {
+            distances.push(squared_distance(left, right).sqrt());
+        }
+    }
+    let bandwidth = if distances.is_empty() {
+        0.0
+    } else {
+        median(distances)
+    };
+    if bandwidth > 0.0 { bandwidth } else { 1.0 }
+}
+
+fn gaussian_scale(bandwidth: Option<f64>, points: &[Vec<f64>]) -> f64 {
+    let bandwidth = bandwidth.unwrap_or_else(|| median_heuristic(points));
+    2.0 * bandwidth * bandwidth
+}
+
+// Segment costs are read off prefix sums in constant time. The linear kernel only needs running
+// sums of the points themselves, while other kernels need the full cumulative Gram matrix.
+enum SegmentCosts {
+    Linear {
+        sums: Vec<Vec<f64>>,
+        squared_norms: Vec<f64>,
+    },
+    Gram {
+        size: usize,
+        block_sums: Vec<f64>,
+        diagonal: Vec<f64>,
+    },
+}
+
+impl SegmentCosts {
+    fn new(points: &[Vec<f64>], kernel: Kernel) -> Self {
+        match kernel {
+            Kernel::Linear => {
+                let dimension = points[0].len();
+                let mut sums = vec![vec![0.0; dimension]];
+                let mut squared_norms = vec![0.0];
+                for point in points {
+                    let next_sum: Vec<f64> = sums[sums.len() - 1]
+                        .iter()
+                        .zip(point.iter())
+                        .map(|(s, x)| s + x)
+                        .collect();
+                    let next_norm = squared_norms[squared_norms.len() - 1] +
+                        point.iter().map(|x| x * x).sum::<f64>();
+                    sums.push(next_sum);
+                    squared_norms.push(next_norm);
+                }
+                SegmentCosts::Linear {
+                    sums,
+                    squared_norms,
+                }
+            }
+            Kernel::Gaussian(bandwidth) => {
+                let scale = gaussian_scale(bandwidth, points);
+                let n = points.len();
+                let size = n + 1;
+                let mut block_sums = vec![0.0; size * size];
+                for i in 0..n {
+                    let mut row_sum = 0.0;
+                    for j in 0..n {
+                        row_sum += (-squared_distance(&points[i], &points[j]) / scale).exp();
+                        block_sums[(i + 1) * size + j + 1] = block_sums[i * size + j + 1] +
+                            row_sum;
+                    }
+                }
+                let diagonal = (0..size).map(|i| i as f64).collect();
+                SegmentCosts::Gram {
+                    size,
+                    block_sums,
+                    diagonal,
+                }
+            }
+        }
+    }
+
+    // Cost of the segment covering observations `start..end`.
+    fn cost(&self, start: usize, end: usize) -> f64 {
+        let len = (end - start) as f64;
+        match *self {
+            SegmentCosts::Linear {
+                ref sums,
+                ref squared_norms,
+            } => {
+                let sum_norm: f64 = sums[end]
+                    .iter()
+                    .zip(sums[start].iter())
+                    .map(|(e, s)| (e - s) * (e - s))
+                    .sum();
+                squared_norms[end] - squared_norms[start] - sum_norm / len
+            }
+            SegmentCosts::Gram {
+                size,
+                ref block_sums,
+                ref diagonal,
+            } => {
+                let block = block_sums[end * size + end] - block_sums[start * size + end] -
+                    block_sums[end * size + start] +
+                    block_sums[start * size + start];
+                diagonal[end] - diagonal[start] - block / len
+            }
+        }
+    }
+}
+
+// The costs of `0..location` and of `location..n` for every location, with empty sides costing
+// nothing. Only the blocks along the diagonal are summed, so the Gaussian kernel needs linear
+// rather than quadratic memory.
+fn split_costs(points: &[Vec<f64>], kernel: Kernel) -> Vec<(f64, f64)> {
+    let n = points.len();
+    match kernel {
+        Kernel::Linear => {
+            let costs = SegmentCosts::new(points, kernel);
+            (0..(n + 1))
+                .map(|location| {
+                    (
+                        if location == 0 { 0.0 } else { costs.cost(0, location) },
+                        if location == n { 0.0 } else { costs.cost(location, n) },
+                    )
+                })
+                .collect()
+        }
+        Kernel::Gaussian(bandwidth) => {
+            let scale = gaussian_scale(bandwidth, points);
+            let kernel =
+                |i: usize, j: usize| (-squared_distance(&points[i], &points[j]) / scale).exp();
+            let mut costs = vec![(0.0, 0.0); n + 1];
+            let mut block = 0.0;
+            for i in 0..n {
+                block += 2.0 * (0..i).map(|j| kernel(i, j)).sum::<f64>() + 1.0;
+                let len = (i + 1) as f64;
+                costs[i + 1].0 = len - block / len;
+            }
+            block = 0.0;
+            for i in (0..n).rev() {
+                block += 2.0 * ((i + 1)..n).map(|j| kernel(i, j)).sum::<f64>() + 1.0;
+                let len = (n - i) as f64;
+                costs[i].1 = len - block / len;
+            }
+            costs
+        }
+    }
+}
+
+// Optimal partitions of `0..n` into `1..=max_segments` segments, by dynamic programming. The
+// returned `boundaries[k][end]` is the start of the last segment of the best partition of
+// `0..end` into `k + 1` segments.
+struct Partitions {
+    costs: Vec<Vec<f64>>,
+    boundaries: Vec<Vec<usize>>,
+}
+
+fn partition(costs: &SegmentCosts, n: usize, min_len: usize, max_segments: usize) -> Partitions {
+    let mut table = vec![vec![f64::INFINITY; n + 1]];
+    let mut boundaries = vec![vec![0; n + 1]];
+    for (end, cost) in table[0].iter_mut().enumerate().skip(min_len) {
+        *cost = costs.cost(0, end);
+    }
+    for k in 1..max_segments {
+        let mut row = vec![f64::INFINITY; n + 1];
+        let mut row_boundaries = vec![0; n + 1];
+        for end in ((k + 1) * min_len)..(n + 1) {
+            let previous = &table[k - 1];
+            for (start, previous_cost) in previous
+                .iter()
+                .enumerate()
+                .take(end - min_len + 1)
+                .skip(k * min_len)
+            {
+                let candidate = previous_cost + costs.cost(start, end);
+                if candidate < row[end] {
+                    row[end] = candidate;
+                    row_boundaries[end] = start;
+                }
+            }
+        }
+        table.push(row);
+        boundaries.push(row_boundaries);
+    }
+    Partitions {
+        costs: table,
+        boundaries,
+    }
+}
+
+impl Partitions {
+    fn changepoints(&self, n: usize, num_changepoints: usize) -> Vec<usize> {
+        let mut result = Vec::with_capacity(num_changepoints);
+        let mut end = n;
+        for k in (1..(num_changepoints + 1)).rev() {
+            end = self.boundaries[k][end];
+            result.push(end);
+        }
+        result.reverse();
+        result
+    }
+}
+
+// The penalty shape of Arlot, Celisse and Harchaoui, for a segmentation into `segments` pieces.
+fn penalty_shape(segments: usize, n: usize) -> f64 {
+    let segments = segments as f64;
+    let n = n as f64;
+    segments / n * (1.0 + (n / segments).ln())
+}
+
+// The slope heuristic: for large models the normalised cost decreases linearly in the penalty
+// shape, and twice the magnitude of that slope is the minimal penalty constant doubled.
+fn slope_heuristic_constant(costs: &[f64], n: usize) -> f64 {
+    let first = costs.len() / 2;
+    let points: Vec<(f64, f64)> = (first..costs.len())
+        .map(|changepoints| {
+            (penalty_shape(changepoints + 1, n), costs[changepoints] / n as f64)
+        })
+        .collect();
+    if points.len() < 2 {
+        return 0.0;
+    }
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / count;
+    let covariance: f64 = points
+        .iter()
+        .map(|&(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|&(x, _)| (x - mean_x) * (x - mean_x)).sum();
+    if variance <= 0.0 {
+        0.0
+    } else {
+        (-2.0 * covariance / variance).max(0.0)
+    }
+}
+
+fn select_penalized(costs: &[f64], n: usize, constant: f64) -> usize {
+    (0..costs.len())
+        .map(|changepoints| {
+            let criterion = costs[changepoints] / n as f64 +
+                constant * penalty_shape(changepoints + 1, n);
+            (changepoints, criterion)
+        })
+        .fold((0, f64::INFINITY), |best, candidate| if candidate.1 < best.1 {
+            candidate
+        } else {
+            best
+        })
+        .0
+}
+
+fn rows_to_points<F: Float>(rows: &[Vec<NonNaN<F>>]) -> Result<Vec<Vec<f64>>> {
+    check_dimensions(rows)?;
+    Ok(
+        rows.iter()
+            .map(|row| row.iter().map(to_f64).collect())
+            .collect(),
+    )
+}
+
+/// Kernel changepoint detection: the optimal partition of the series into segments that are as
+/// homogeneous as possible in the feature space of the chosen `Kernel`.
+///
+/// Segments are at least `min_segment_length` observations long and at most `max_changepoints`
+/// changepoints are considered, with the reported number picked by the `ModelSelection`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedKCP"))]
+pub struct KCP {
+    kernel: Kernel,
+    min_segment_length: usize,
+    max_changepoints: usize,
+    model_selection: ModelSelection,
+}
+
+// Deserialized configurations are checked like those built by `KCP::new`.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedKCP {
+    kernel: Kernel,
+    min_segment_length: usize,
+    max_changepoints: usize,
+    model_selection: ModelSelection,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedKCP> for KCP {
+    type Error = Error;
+
+    fn try_from(unchecked: UncheckedKCP) -> Result<Self> {
+        let detector = KCP {
+            kernel: unchecked.kernel,
+            min_segment_length: unchecked.min_segment_length,
+            max_changepoints: unchecked.max_changepoints,
+            model_selection: unchecked.model_selection,
+        };
+        detector.check_parameters()?;
+        Ok(detector)
+    }
+}
+
+impl KCP {
+    /// The longest series `segment` and `segment_rows` accept with a Gaussian kernel, whose
+    /// cumulative Gram matrix takes `8 * (n + 1)^2` bytes, about 200 MB at this length.
+    /// Single splits from `find_candidate` only need linear memory and have no such limit.
+    pub const MAX_GAUSSIAN_SEGMENTATION_LENGTH: usize = 5_000;
+
+    pub fn new(kernel: Kernel, min_segment_length: usize, max_changepoints: usize) -> Self {
+        KCP {
+            kernel,
+            min_segment_length,
+            max_changepoints,
+            model_selection: ModelSelection::SlopeHeuristic,
+        }
+    }
+
+    pub fn with_model_selection(mut self, model_selection: ModelSelection) -> Self {
+        self.model_selection = model_selection;
+        self
+    }
+
+    pub fn segment<F: Float>(&self, observations: &[NonNaN<F>]) -> Result<KernelSegmentation> {
+        let points: Vec<Vec<f64>> = to_f64s(observations)
+            .into_iter()
+            .map(|value| vec![value])
+            .collect();
+        self.segment_points(&points)
+    }
+
+    /// Segments multivariate observations, where every row is one observation.
+    pub fn segment_rows<F: Float>(&self, rows: &[Vec<NonNaN<F>>]) -> Result<KernelSegmentation> {
+        self.segment_points(&rows_to_points(rows)?)
+    }
+
+    pub(crate) fn check_parameters(&self) -> Result<()> {
+        if self.min_segment_length == 0 {
+            return Err(
+                ErrorKind::InvalidParameter(
+                    "min_segment_length".to_string(),
+                    "must be at least 1".to_string(),
+                ).into(),
+            );
+        }
+        if let Kernel::Gaussian(Some(bandwidth)) = self.kernel {
+            if !(bandwidth > 0.0 && bandwidth.is_finite()) {
+                return Err(
+                    ErrorKind::InvalidParameter(
+                        "bandwidth".to_string(),
+                        format!("{} is not a finite positive number", bandwidth),
+                    ).into(),
+                );
+            }
+        }
+        match self.model_selection {
+            ModelSelection::Penalty(constant) if !(constant >= 0.0 && constant.is_finite()) => {
+                Err(
+                    ErrorKind::InvalidParameter(
+                        "model_selection".to_string(),
+                        format!("penalty {} is not a finite non-negative number", constant),
+                    ).into(),
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_length(&self, n: usize) -> Result<()> {
+        self.check_parameters()?;
+        check_observations(n, self.min_segment_length * 2)
+    }
+
+    fn segment_points(&self, points: &[Vec<f64>]) -> Result<KernelSegmentation> {
+        let n = points.len();
+        self.check_length(n)?;
+        if let Kernel::Gaussian(_) = self.kernel {
+            if n > KCP::MAX_GAUSSIAN_SEGMENTATION_LENGTH {
+                return Err(
+                    ErrorKind::TooManyValues(n, KCP::MAX_GAUSSIAN_SEGMENTATION_LENGTH).into(),
+                );
+            }
+        }
+        let max_segments = self.max_changepoints.saturating_add(1).min(
+            n / self.min_segment_length,
+        );
+        let segment_costs = SegmentCosts::new(points, self.kernel);
+        let partitions = partition(&segment_costs, n, self.min_segment_length, max_segments);
+        let costs: Vec<f64> = partitions.costs.iter().map(|row| row[n]).collect();
+        let num_changepoints = match self.model_selection {
+            ModelSelection::Fixed(num_changepoints) => num_changepoints.min(costs.len() - 1),
+            ModelSelection::Penalty(constant) => select_penalized(&costs, n, constant),
+            ModelSelection::SlopeHeuristic => {
+                let constant = slope_heuristic_constant(&costs, n);
+                select_penalized(&costs, n, constant)
+            }
+        };
+        Ok(KernelSegmentation {
+            changepoints: partitions.changepoints(n, num_changepoints),
+            cost: costs[num_changepoints],
+            costs,
+        })
+    }
+
+    // Every single split, scored by how much it lowers the kernel cost of the whole series.
+    fn split_scores(&self, points: &[Vec<f64>]) -> Result<Vec<(f64, usize)>> {
+        let n = points.len();
+        self.check_length(n)?;
+        let costs = split_costs(points, self.kernel);
+        let total_cost = costs[n].0;
+        Ok(
+            (self.min_segment_length..(n - self.min_segment_length + 1))
+                .map(|location| {
+                    let (before, after) = costs[location];
+                    ((total_cost - before - after).max(0.0), location)
+                })
+                .collect(),
+        )
+    }
+
+    // The best single split, keeping the first of equally good ones.
+    fn best_split(&self, points: &[Vec<f64>]) -> Result<(f64, usize)> {
+        Ok(self.split_scores(points)?.into_iter().fold(
+            (-1.0, 0),
+            |best, candidate| if candidate.0 > best.0 {
+                candidate
+            } else {
+                best
+            },
+        ))
+    }
+
+    fn describe_parameters(&self) -> Vec<(String, String)> {
+        let mut parameters = match self.kernel {
+            Kernel::Gaussian(bandwidth) => {
+                let mut parameters = vec![("kernel".to_string(), "gaussian".to_string())];
+                if let Some(bandwidth) = bandwidth {
+                    parameters.push(("bandwidth".to_string(), bandwidth.to_string()));
+                }
+                parameters
+            }
+            Kernel::Linear => vec![("kernel".to_string(), "linear".to_string())],
+        };
+        parameters.push((
+            "min_segment_length".to_string(),
+            self.min_segment_length.to_string(),
+        ));
+        parameters.push((
+            "max_changepoints".to_string(),
+            self.max_changepoints.to_string(),
+        ));
+        parameters.push((
+            "model_selection".to_string(),
+            match self.model_selection {
+                ModelSelection::SlopeHeuristic => "slope_heuristic".to_string(),
+                ModelSelection::Penalty(constant) => format!("penalty:{}", constant),
+                ModelSelection::Fixed(num_changepoints) => format!("fixed:{}", num_changepoints),
+            },
+        ));
+        parameters
+    }
+}
+
+fn to_candidate<F: Float>((statistic, location): (f64, usize)) -> BestCandidate<NonNaN<F>> {
+    BestCandidate {
+        statistic: from_f64(statistic),
+        location,
+    }
+}
+
+impl<F: Float> ChangePointDetector<NonNaN<F>> for KCP {
+    type Statistic = NonNaN<F>;
+
+    fn find_candidate(&self, observations: &[NonNaN<F>]) -> Result<BestCandidate<NonNaN<F>>> {
+        let points: Vec<Vec<f64>> = to_f64s(observations)
+            .into_iter()
+            .map(|value| vec![value])
+            .collect();
+        self.best_split(&points).map(to_candidate)
+    }
+
+    fn min_observations(&self) -> usize {
+        self.min_segment_length * 2
+    }
+
+    fn name(&self) -> String {
+        "kcp".to_string()
+    }
+
+    fn parameters(&self) -> Vec<(String, String)> {
+        self.describe_parameters()
+    }
+
+    fn statistic_curve(
+        &self,
+        observations: &[NonNaN<F>],
+    ) -> Result<Option<Vec<BestCandidate<NonNaN<F>>>>> {
+        let points: Vec<Vec<f64>> = to_f64s(observations)
+            .into_iter()
+            .map(|value| vec![value])
+            .collect();
+        let scores = self.split_scores(&points)?;
+        Ok(Some(scores.into_iter().map(to_candidate).collect()))
+    }
+
+    /// With the linear kernel, the statistic is the drop in the residual sum of squares from
+    /// splitting the mean. Scaled by the residual variance it tends to the maximum of a squared,
+    /// standardized Brownian bridge when there is no change.
+    fn asymptotic_p_value(
+        &self,
+        observations: &[NonNaN<F>],
+        candidate: &BestCandidate<NonNaN<F>>,
+    ) -> Result<f64> {
+        if let Kernel::Gaussian(_) = self.kernel {
+            return Err(ErrorKind::NoAsymptoticDistribution.into());
+        }
+        let values = to_f64s(observations);
+        let n = values.len();
+        self.check_length(n)?;
+        let location = candidate.location;
+        if location < self.min_segment_length || location > n - self.min_segment_length {
+            return Err(
+                ErrorKind::InvalidParameter(
+                    "location".to_string(),
+                    format!(
+                        "{} leaves a segment shorter than {} of the {} observations",
+                        location,
+                        self.min_segment_length,
+                        n
+                    ),
+                ).into(),
+            );
+        }
+        let residual_sum_of_squares = |segment: &[f64]| {
+            let mean = segment.iter().sum::<f64>() / segment.len() as f64;
+            segment.iter().map(|value| (value - mean) * (value - mean)).sum::<f64>()
+        };
+        let (before, after) = values.split_at(location);
+        let residuals = residual_sum_of_squares(before) + residual_sum_of_squares(after);
+        let variance = residuals / (n as f64 - 2.0).max(1.0);
+        let statistic = to_f64(&candidate.statistic);
+        if variance <= 0.0 {
+            return Ok(if statistic > 0.0 { 0.0 } else { 1.0 });
+        }
+        Ok(standardized_bridge_exceedance(
+            (statistic / variance).sqrt(),
+            self.min_segment_length as f64 / n as f64,
+        ))
+    }
+}
+
+impl<F: Float> ChangePointDetector<Vec<NonNaN<F>>> for KCP {
+    type Statistic = NonNaN<F>;
+
+    fn find_candidate(&self, rows: &[Vec<NonNaN<F>>]) -> Result<BestCandidate<NonNaN<F>>> {
+        self.best_split(&rows_to_points(rows)?).map(to_candidate)
+    }
+
+    fn min_observations(&self) -> usize {
+        self.min_segment_length * 2
+    }
+
+    fn name(&self) -> String {
+        "kcp".to_string()
+    }
+
+    fn parameters(&self) -> Vec<(String, String)> {
+        self.describe_parameters()
+    }
+
+    fn statistic_curve(
+        &self,
+        rows: &[Vec<NonNaN<F>>],
+    ) -> Result<Option<Vec<BestCandidate<NonNaN<F>>>>> {
+        let scores = self.split_scores(&rows_to_points(rows)?)?;
+        Ok(Some(scores.into_iter().map(to_candidate).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use mersenne_twister::MersenneTwister;
+    use generator::normal_series;
+
+    #[test]
+    fn segment_costs_match_direct_computation() {
+        let points = vec![vec![1.0], vec![2.0], vec![4.0], vec![7.0]];
+        let linear = SegmentCosts::new(&points, Kernel::Linear);
+        // Sum of squared deviations from the mean of 1, 2 and 4.
+        assert!((linear.cost(0, 3) - 4.666666666666667).abs() < 1e-9);
+        let gaussian = SegmentCosts::new(&points, Kernel::Gaussian(Some(1.0)));
+        let k = |a: f64, b: f64| (-(a - b) * (a - b) / 2.0).exp();
+        let expected = 2.0 - (2.0 + 2.0 * k(4.0, 7.0)) / 2.0;
+        assert!((gaussian.cost(2, 4) - expected).abs() < 1e-9);
+        for &kernel in &[Kernel::Linear, Kernel::Gaussian(Some(1.0))] {
+            let full = SegmentCosts::new(&points, kernel);
+            let splits = split_costs(&points, kernel);
+            assert!((splits[4].0 - full.cost(0, 4)).abs() < 1e-9);
+            for (location, &(before, after)) in splits.iter().enumerate().take(4).skip(1) {
+                assert!((before - full.cost(0, location)).abs() < 1e-9);
+                assert!((after - full.cost(location, 4)).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn kcp_rejects_bad_parameters_and_long_gaussian_segmentations() {
+        let observations: Vec<NonNaN<f64>> =
+            (0..100).map(|i| NonNaN::new((i % 7) as f64).unwrap()).collect();
+        for &bandwidth in &[0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let detector = KCP::new(Kernel::Gaussian(Some(bandwidth)), 10, 1);
+            match *detector.find_candidate(&observations).unwrap_err().kind() {
+                ErrorKind::InvalidParameter(ref name, _) => assert_eq!(name, "bandwidth"),
+                ref other => panic!("Unexpected error {:?}", other),
+            }
+        }
+        let unsegmented = KCP::new(Kernel::Linear, 0, 1);
+        for result in &[
+            unsegmented.find_candidate(&observations).map(|_| ()),
+            unsegmented.segment(&observations).map(|_| ()),
+        ]
+        {
+            match *result.as_ref().unwrap_err().kind() {
+                ErrorKind::InvalidParameter(ref name, _) => assert_eq!(name, "min_segment_length"),
+                ref other => panic!("Unexpected error {:?}", other),
+            }
+        }
+        let length = KCP::MAX_GAUSSIAN_SEGMENTATION_LENGTH + 1;
+        let long: Vec<NonNaN<f64>> = (0..length).map(|i| NonNaN::new(i as f64).unwrap()).collect();
+        match *KCP::new(Kernel::Gaussian(Some(1.0)), 10, 1)
+            .segment(&long)
+            .unwrap_err()
+            .kind() {
+            ErrorKind::TooManyValues(found, max) => assert_eq!((found, max), (length, length - 1)),
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn kcp_finds_multiple_changes() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let input = normal_series(&mut rng, &[(150, 0.0, 1.0), (100, 4.0, 1.0), (150, 0.0, 3.0)]);
+        let tolerance = 10;
+        for kernel in &[Kernel::Gaussian(None), Kernel::Linear] {
+            let segmentation = KCP::new(*kernel, 20, 8)
+                .with_model_selection(ModelSelection::Fixed(2))
+                .segment(&input)
+                .unwrap();
+            assert_eq!(segmentation.changepoints.len(), 2);
+            assert_eq!(segmentation.costs.len(), 9);
+        }
+        let segmentation = KCP::new(Kernel::Gaussian(None), 20, 8)
+            .segment(&input)
+            .unwrap();
+        assert_eq!(segmentation.changepoints.len(), 2);
+        assert!((segmentation.changepoints[0] as i64 - 150).abs() < tolerance);
+        assert!((segmentation.changepoints[1] as i64 - 250).abs() < tolerance);
+    }
+
+    #[test]
+    fn kcp_reports_no_change_in_noise() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let input = normal_series(&mut rng, &[(400, 0.0, 1.0)]);
+        let segmentation = KCP::new(Kernel::Gaussian(None), 20, 8)
+            .segment(&input)
+            .unwrap();
+        assert!(segmentation.changepoints.is_empty());
+    }
+
+    #[test]
+    fn kcp_segments_rows() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let first = normal_series(&mut rng, &[(100, 0.0, 1.0), (100, 0.0, 1.0)]);
+        let second = normal_series(&mut rng, &[(100, 0.0, 1.0), (100, 3.0, 1.0)]);
+        let rows: Vec<Vec<NonNaN<f64>>> = first
+            .into_iter()
+            .zip(second)
+            .map(|(a, b)| vec![a, b])
+            .collect();
+        let segmentation = KCP::new(Kernel::Gaussian(None), 20, 5)
+            .segment_rows(&rows)
+            .unwrap();
+        assert_eq!(segmentation.changepoints.len(), 1);
+        assert!((segmentation.changepoints[0] as i64 - 100).abs() < 10);
+        let mut ragged = rows.clone();
+        ragged[7].pop();
+        assert!(KCP::new(Kernel::Linear, 20, 5).segment_rows(&ragged).is_err());
+    }
+
+    #[test]
+    fn kcp_single_split_candidate() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let input = normal_series(&mut rng, &[(100, 10.0, 5.0), (200, 25.0, 5.0)]);
+        let best_candidate = KCP::new(Kernel::Gaussian(None), 30, 1)
+            .find_candidate(&input)
+            .unwrap();
+        assert!((best_candidate.location as i64 - 100).abs() < 10);
+    }
+
+    #[test]
+    fn asymptotic_p_value_needs_a_location_between_full_segments() {
+        let observations: Vec<NonNaN<f64>> =
+            (0..40).map(|i| NonNaN::new((i % 7) as f64).unwrap()).collect();
+        let detector = KCP::new(Kernel::Linear, 10, 1);
+        let candidate = |location| {
+            BestCandidate {
+                statistic: NonNaN::new(1.0).unwrap(),
+                location,
+            }
+        };
+        for &location in &[0, 9, 31, 40, 1000] {
+            match *detector
+                .asymptotic_p_value(&observations, &candidate(location))
+                .unwrap_err()
+                .kind() {
+                ErrorKind::InvalidParameter(ref name, _) => assert_eq!(name, "location"),
+                ref other => panic!("Unexpected error {:?}", other),
+            }
+        }
+        for &location in &[10, 30] {
+            assert!(detector.asymptotic_p_value(&observations, &candidate(location)).is_ok());
+        }
+    }
+
+    #[test]
+    fn kcp_reports_parameters_and_its_curve() {
+        let observations: Vec<NonNaN<f64>> = (0..40)
+            .map(|i| NonNaN::new(if i < 25 { 1.0 } else { 4.0 } + (i % 4) as f64 * 0.1).unwrap())
+            .collect();
+        let detector = KCP::new(Kernel::Linear, 5, 2);
+        assert_eq!(
+            ChangePointDetector::<NonNaN<f64>>::describe(&detector),
+            "kcp(kernel=linear, min_segment_length=5, max_changepoints=2, \
+             model_selection=slope_heuristic)"
+        );
+        let curve = detector.statistic_curve(&observations).unwrap().unwrap();
+        assert_eq!(curve.len(), 31);
+        let best = detector.find_candidate(&observations).unwrap();
+        assert_eq!(best.location, 25);
+        assert_eq!(curve.into_iter().max().unwrap().statistic, best.statistic);
+    }
+}
@@ -3,3 +3,20 @@ pub mod non_nan;
 pub mod best_candidate;
 pub mod changepoint;
 pub mod permutation_test;
+pub mod dispersion;
+pub mod kcp;
+pub mod multivariate;
+pub mod trend;
+pub mod seasonal;
+pub mod stats;
+pub mod anomaly;
+pub mod time_series;
+pub mod resample;
+pub mod cleaning;
+pub mod batch;
+pub mod parallelism;
+pub mod cancellation;
+pub mod progress;
+pub mod significance;
+pub mod registry;
+pub mod multiple_testing;
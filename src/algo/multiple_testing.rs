@@ -0,0 +1,155 @@
+use algo::permutation_test::PermutationTestResult;
+
+use errors::*;
+
+/// Adjustment of p-values for testing many series at once.
+///
+/// `Bonferroni` and `Holm` control the family-wise error rate, `BenjaminiHochberg` the false
+/// discovery rate of independent tests, and `BenjaminiYekutieli` the false discovery rate under
+/// any dependence between the tests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Correction {
+    Bonferroni,
+    Holm,
+    BenjaminiHochberg,
+    BenjaminiYekutieli,
+}
+
+/// Adjusted p-values in the order of the tests, and the indices of the tests whose adjusted
+/// p-value is at most the requested level.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CorrectedResults {
+    pub adjusted_p_values: Vec<f64>,
+    pub discoveries: Vec<usize>,
+}
+
+fn check_probability(name: &str, value: f64) -> Result<()> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(
+            ErrorKind::InvalidParameter(
+                name.to_string(),
+                format!("{} is not between 0 and 1", value),
+            ).into(),
+        )
+    }
+}
+
+/// Adjusts the p-values of a family of tests, returning the adjusted p-values in the same order.
+pub fn adjust_p_values(p_values: &[f64], correction: Correction) -> Result<Vec<f64>> {
+    for &p_value in p_values {
+        check_probability("p_value", p_value)?;
+    }
+    let m = p_values.len() as f64;
+    let mut order: Vec<usize> = (0..p_values.len()).collect();
+    order.sort_by(|&a, &b| {
+        p_values[a].partial_cmp(&p_values[b]).expect(
+            "p-values were checked to be between 0 and 1",
+        )
+    });
+    let mut adjusted = vec![0.0; p_values.len()];
+    match correction {
+        Correction::Bonferroni => {
+            for (adjusted, p_value) in adjusted.iter_mut().zip(p_values) {
+                *adjusted = (p_value * m).min(1.0);
+            }
+        }
+        // Step-down: the smallest p-value is multiplied by m, the next by m - 1 and so on, and
+        // an adjusted p-value is never smaller than that of a more significant test.
+        Correction::Holm => {
+            let mut running_max: f64 = 0.0;
+            for (rank, &index) in order.iter().enumerate() {
+                running_max = running_max.max((p_values[index] * (m - rank as f64)).min(1.0));
+                adjusted[index] = running_max;
+            }
+        }
+        // Step-up: the p-value of rank k is multiplied by m / k, and an adjusted p-value is
+        // never larger than that of a less significant test.
+        Correction::BenjaminiHochberg |
+        Correction::BenjaminiYekutieli => {
+            let dependence_factor = match correction {
+                Correction::BenjaminiYekutieli => (1..(p_values.len() + 1))
+                    .map(|k| 1.0 / k as f64)
+                    .sum(),
+                _ => 1.0,
+            };
+            let mut running_min: f64 = 1.0;
+            for (rank, &index) in order.iter().enumerate().rev() {
+                let scaled = p_values[index] * m * dependence_factor / (rank + 1) as f64;
+                running_min = running_min.min(scaled);
+                adjusted[index] = running_min;
+            }
+        }
+    }
+    Ok(adjusted)
+}
+
+/// Adjusts the p-values of permutation tests on many series, reporting as discoveries the tests
+/// which remain significant at `level`, the family-wise error rate or false discovery rate
+/// depending on the `Correction`.
+pub fn correct(
+    results: &[PermutationTestResult],
+    correction: Correction,
+    level: f64,
+) -> Result<CorrectedResults> {
+    check_probability("level", level)?;
+    let p_values: Vec<f64> = results.iter().map(|result| result.p_value).collect();
+    let adjusted_p_values = adjust_p_values(&p_values, correction)?;
+    let discoveries = adjusted_p_values
+        .iter()
+        .enumerate()
+        .filter(|&(_, &p_value)| p_value <= level)
+        .map(|(index, _)| index)
+        .collect();
+    Ok(CorrectedResults {
+        adjusted_p_values,
+        discoveries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const P_VALUES: [f64; 4] = [0.01, 0.04, 0.03, 0.005];
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn adjustments_match_reference_values() {
+        let adjust = |correction| adjust_p_values(&P_VALUES, correction).unwrap();
+        assert_close(&adjust(Correction::Bonferroni), &[0.04, 0.16, 0.12, 0.02]);
+        assert_close(&adjust(Correction::Holm), &[0.03, 0.06, 0.06, 0.02]);
+        assert_close(&adjust(Correction::BenjaminiHochberg), &[0.02, 0.04, 0.04, 0.02]);
+        let c = 1.0 + 1.0 / 2.0 + 1.0 / 3.0 + 1.0 / 4.0;
+        assert_close(
+            &adjust(Correction::BenjaminiYekutieli),
+            &[0.02 * c, 0.04 * c, 0.04 * c, 0.02 * c],
+        );
+        assert!(adjust_p_values(&[0.5, 1.5], Correction::Holm).is_err());
+    }
+
+    #[test]
+    fn discoveries_at_level() {
+        let results: Vec<PermutationTestResult> = P_VALUES
+            .iter()
+            .map(|&p_value| PermutationTestResult {
+                p_value,
+                changepoint_index: 0,
+            })
+            .collect();
+        let bonferroni = correct(&results, Correction::Bonferroni, 0.05).unwrap();
+        assert_eq!(bonferroni.discoveries, vec![0, 3]);
+        let fdr = correct(&results, Correction::BenjaminiHochberg, 0.05).unwrap();
+        assert_eq!(fdr.discoveries, vec![0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,257 @@
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use algo::best_candidate::BestCandidate;
+use algo::changepoint::{ChangePointDetector, check_observations};
+use algo::dispersion::{median_absolute_deviation, median_of_sorted};
+use algo::edm_x::edm_x::{edm_x_curve, HeapNum};
+
+use errors::*;
+
+/// How the per-dimension statistics at a split location are combined into one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Aggregation {
+    Sum,
+    Max,
+}
+
+/// Checks that every row is non-empty and has the same length as the first, returning that
+/// common dimension.
+pub fn check_dimensions<T>(rows: &[Vec<T>]) -> Result<usize> {
+    let dimension = rows.first().map(|row| row.len()).unwrap_or(0);
+    match rows.iter().position(
+        |row| row.len() != dimension || row.is_empty(),
+    ) {
+        Some(index) => Err(
+            ErrorKind::DimensionMismatch(index, dimension, rows[index].len()).into(),
+        ),
+        None => Ok(dimension),
+    }
+}
+
+// Each column is centred on its median and scaled by its median absolute deviation, so that
+// dimensions measured in different units contribute comparably to the aggregate.
+fn standardized_columns<T: HeapNum>(rows: &[Vec<T>], dimension: usize) -> Vec<Vec<T>> {
+    (0..dimension)
+        .map(|column| {
+            let values: Vec<T> = rows.iter().map(|row| row[column].clone()).collect();
+            let mut sorted = values.clone();
+            sorted.sort();
+            let median = median_of_sorted(&sorted);
+            let scale = median_absolute_deviation(&sorted);
+            values
+                .into_iter()
+                .map(|value| if scale == T::zero() {
+                    value - median.clone()
+                } else {
+                    (value - median.clone()) / scale.clone()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// EDM-X over observations with several dimensions, where every observation is a row of the
+/// same length.
+///
+/// Each dimension is robustly standardised and run through EDM-X on its own, and the
+/// per-dimension statistics at every split location are combined with the `Aggregation`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedMultivariateEDMX"))]
+pub struct MultivariateEDMX {
+    delta: usize,
+    aggregation: Aggregation,
+}
+
+// Deserialized configurations are checked before they can be run.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedMultivariateEDMX {
+    delta: usize,
+    aggregation: Aggregation,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedMultivariateEDMX> for MultivariateEDMX {
+    type Error = Error;
+
+    fn try_from(unchecked: UncheckedMultivariateEDMX) -> Result<Self> {
+        let detector = MultivariateEDMX::new(unchecked.delta, unchecked.aggregation);
+        detector.check_parameters()?;
+        Ok(detector)
+    }
+}
+
+impl MultivariateEDMX {
+    pub fn new(delta: usize, aggregation: Aggregation) -> Self {
+        MultivariateEDMX { delta, aggregation }
+    }
+
+    // As for EDM-X, the medians on either side of a split are undefined for a delta of zero.
+    fn check_parameters(&self) -> Result<()> {
+        if self.delta == 0 {
+            return Err(
+                ErrorKind::InvalidParameter("delta".to_string(), "must be at least 1".to_string())
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl MultivariateEDMX {
+    // The aggregated statistic at every split location, in order of location.
+    fn aggregated_curve<T: HeapNum + From<f64>>(
+        &self,
+        rows: &[Vec<T>],
+    ) -> Result<Vec<BestCandidate<T>>> {
+        self.check_parameters()?;
+        check_observations(rows.len(), self.delta * 2 + 1)?;
+        let dimension = check_dimensions(rows)?;
+        let curves: Vec<Vec<BestCandidate<T>>> = standardized_columns(rows, dimension)
+            .iter()
+            .map(|column| edm_x_curve(column, self.delta))
+            .collect();
+        let aggregation = self.aggregation;
+        Ok(
+            (0..curves[0].len())
+                .map(|position| {
+                    let statistics = curves.iter().map(|curve| curve[position].statistic.clone());
+                    let statistic = match aggregation {
+                        Aggregation::Sum => statistics.fold(T::zero(), |sum, value| sum + value),
+                        Aggregation::Max => statistics.max().expect(
+                            "check_dimensions ensures there is at least one dimension",
+                        ),
+                    };
+                    BestCandidate {
+                        statistic,
+                        location: curves[0][position].location,
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<T: HeapNum + From<f64>> ChangePointDetector<Vec<T>> for MultivariateEDMX {
+    type Statistic = T;
+
+    fn find_candidate(&self, rows: &[Vec<T>]) -> Result<BestCandidate<T>> {
+        Ok(self.aggregated_curve(rows)?.into_iter().max().expect(
+            "The curve is never empty for more than twice delta rows",
+        ))
+    }
+
+    fn min_observations(&self) -> usize {
+        self.delta * 2 + 1
+    }
+
+    fn name(&self) -> String {
+        "multivariate-edmx".to_string()
+    }
+
+    fn parameters(&self) -> Vec<(String, String)> {
+        let aggregation = match self.aggregation {
+            Aggregation::Sum => "sum",
+            Aggregation::Max => "max",
+        };
+        vec![
+            ("delta".to_string(), self.delta.to_string()),
+            ("aggregation".to_string(), aggregation.to_string()),
+        ]
+    }
+
+    fn statistic_curve(&self, rows: &[Vec<T>]) -> Result<Option<Vec<BestCandidate<T>>>> {
+        self.aggregated_curve(rows).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::non_nan::NonNaN;
+    use algo::permutation_test::permutation_test;
+    use rand::SeedableRng;
+    use rand::distributions::{IndependentSample, Normal};
+    use mersenne_twister::MersenneTwister;
+
+    // Three dimensions on very different scales, where only the first shifts its mean.
+    fn shifted_rows(
+        rng: &mut MersenneTwister,
+        num_before: usize,
+        num_after: usize,
+    ) -> Vec<Vec<NonNaN<f64>>> {
+        let before_change_dist = Normal::new(10.0, 5.0);
+        let after_change_dist = Normal::new(25.0, 5.0);
+        let memory_dist = Normal::new(1000.0, 100.0);
+        let latency_dist = Normal::new(0.5, 0.1);
+        (0..(num_before + num_after))
+            .map(|i| {
+                let cpu_dist = if i < num_before {
+                    before_change_dist
+                } else {
+                    after_change_dist
+                };
+                vec![
+                    NonNaN::new(cpu_dist.ind_sample(rng)).unwrap(),
+                    NonNaN::new(memory_dist.ind_sample(rng)).unwrap(),
+                    NonNaN::new(latency_dist.ind_sample(rng)).unwrap(),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn multivariate_edm_x_finds_change_in_one_dimension() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let rows = shifted_rows(&mut rng, 150, 250);
+        let tolerance = 20;
+        for aggregation in &[Aggregation::Sum, Aggregation::Max] {
+            let best_candidate = MultivariateEDMX::new(20, *aggregation)
+                .find_candidate(&rows)
+                .unwrap();
+            assert!((best_candidate.location as i64 - 150).abs() < tolerance);
+        }
+    }
+
+    #[test]
+    fn multivariate_permutation_test_permutes_rows() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let rows = shifted_rows(&mut rng, 150, 150);
+        let algorithm = MultivariateEDMX::new(20, Aggregation::Sum);
+        let full_test = permutation_test(&algorithm, rng, 10, &rows).unwrap();
+        assert!(full_test.p_value <= 0.1);
+    }
+
+    #[test]
+    fn ragged_rows_are_rejected() {
+        let mut rows: Vec<Vec<NonNaN<f64>>> = vec![vec![NonNaN::new(1.0).unwrap(); 2]; 50];
+        rows[30].push(NonNaN::new(1.0).unwrap());
+        match *MultivariateEDMX::new(10, Aggregation::Max)
+            .find_candidate(&rows)
+            .unwrap_err()
+            .kind() {
+            ErrorKind::DimensionMismatch(30, 2, 3) => {}
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn multivariate_edm_x_rejects_a_delta_of_zero() {
+        let rows: Vec<Vec<NonNaN<f64>>> =
+            (0..10).map(|i| vec![NonNaN::new(i as f64).unwrap(); 2]).collect();
+        let detector = MultivariateEDMX::new(0, Aggregation::Sum);
+        for result in &[
+            detector.find_candidate(&rows).map(|_| ()),
+            detector.statistic_curve(&rows).map(|_| ()),
+        ]
+        {
+            match *result.as_ref().unwrap_err().kind() {
+                ErrorKind::InvalidParameter(ref name, _) => assert_eq!(name, "delta"),
+                ref other => panic!("Unexpected error {:?}", other),
+            }
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use num::{One, Zero, Num, Float};
 use std::cmp::{Ord, Ordering};
 use std::ops::{Add, Sub, Mul, Div, Rem};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serde")]
+use serde::de::Error;
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct NonNaN<F: Float>(F);
 
 impl<F: Float> Copy for NonNaN<F> {}
@@ -24,9 +28,32 @@ impl<F: Float> NonNaN<F> {
 
 impl<F: Float> Eq for NonNaN<F> {}
 
+// Serialized as the plain number, rejecting non-finite numbers when deserializing.
+#[cfg(feature = "serde")]
+impl<F: Float + Serialize> Serialize for NonNaN<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, F: Float + Deserialize<'de>> Deserialize<'de> for NonNaN<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        NonNaN::new(F::deserialize(deserializer)?).ok_or_else(|| {
+            D::Error::custom("expected a finite floating point number")
+        })
+    }
+}
+
+impl<F: Float> PartialOrd for NonNaN<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl<F: Float> Ord for NonNaN<F> {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).expect(
+        self.0.partial_cmp(&other.0).expect(
             "Impossible to create a NaN value for a NonNaN float, so this always is Some.",
         )
     }
@@ -184,7 +211,6 @@ impl From<f64> for NonNaN<f32> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std;
 
     fn get_max_value<F: Float>(_: F) -> F {
         F::max_value()
@@ -209,15 +235,15 @@ mod tests {
 
     #[test]
     fn infinite_and_nan_floats_are_not_non_nan() {
-        assert!(NonNaN::new(std::f32::NAN).is_none());
-        assert!(NonNaN::new(std::f32::INFINITY).is_none());
-        assert!(NonNaN::new(-std::f32::INFINITY).is_none());
+        assert!(NonNaN::new(f32::NAN).is_none());
+        assert!(NonNaN::new(f32::INFINITY).is_none());
+        assert!(NonNaN::new(-f32::INFINITY).is_none());
     }
 
     #[test]
     fn clip_infinite_values() {
-        let clipped_infinite = clip_to_finite(std::f32::INFINITY);
-        let clipped_neg_infinite = clip_to_finite(-std::f32::INFINITY);
+        let clipped_infinite = clip_to_finite(f32::INFINITY);
+        let clipped_neg_infinite = clip_to_finite(-f32::INFINITY);
         let max_value = get_max_value(0.0);
         let min_value = -get_max_value(0.0);
         assert_eq!(clipped_infinite.value(), max_value);
@@ -303,7 +329,7 @@ mod tests {
         for (result, expectation) in normal_non_nan_values.iter().zip(normal_float_values.iter()) {
             assert_eq!(result.value(), *expectation);
         }
-        let bad_float_values = vec![0.0, 1.0, std::f32::INFINITY, 2.0];
+        let bad_float_values = vec![0.0, 1.0, f32::INFINITY, 2.0];
         let bad_non_nan_values = to_non_nans(&bad_float_values);
         assert!(bad_non_nan_values.is_none());
     }
@@ -0,0 +1,75 @@
+#[cfg(feature = "parallel")]
+use std::sync::Arc;
+#[cfg(feature = "parallel")]
+use rayon;
+
+#[cfg(feature = "parallel")]
+use errors::*;
+
+/// Where the work of a permutation test or a batch runs.
+#[derive(Clone, Debug)]
+pub enum Parallelism {
+    /// Entirely on the calling thread.
+    Sequential,
+    /// On the global rayon thread pool.
+    #[cfg(feature = "parallel")]
+    GlobalPool,
+    /// On a dedicated rayon thread pool, isolating detection from other work on the global pool.
+    #[cfg(feature = "parallel")]
+    Pool(Arc<rayon::ThreadPool>),
+}
+
+impl Default for Parallelism {
+    #[cfg(feature = "parallel")]
+    fn default() -> Self {
+        Parallelism::GlobalPool
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn default() -> Self {
+        Parallelism::Sequential
+    }
+}
+
+impl Parallelism {
+    /// A dedicated pool with a fixed number of threads.
+    #[cfg(feature = "parallel")]
+    // rayon 0.8 only exposes the deprecated `Configuration` to build pools with.
+    #[allow(deprecated)]
+    pub fn dedicated_pool(num_threads: usize) -> Result<Self> {
+        if num_threads == 0 {
+            return Err(
+                ErrorKind::InvalidParameter(
+                    "num_threads".to_string(),
+                    "a pool needs at least one thread".to_string(),
+                ).into(),
+            );
+        }
+        let configuration = rayon::Configuration::new().num_threads(num_threads);
+        let pool = rayon::ThreadPool::new(configuration).map_err(|error| {
+            Error::from(error.to_string())
+        })?;
+        Ok(Parallelism::Pool(Arc::new(pool)))
+    }
+
+    pub fn is_sequential(&self) -> bool {
+        match *self {
+            Parallelism::Sequential => true,
+            #[cfg(feature = "parallel")]
+            _ => false,
+        }
+    }
+
+    /// Runs `op` so that any rayon work it starts happens on this pool.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn install<OP, R>(&self, op: OP) -> R
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        match *self {
+            Parallelism::Pool(ref pool) => pool.install(op),
+            _ => op(),
+        }
+    }
+}
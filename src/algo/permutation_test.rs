@@ -1,23 +1,30 @@
 use algo::best_candidate::BestCandidate;
+use algo::cancellation::CancellationToken;
 use algo::changepoint::ChangePointDetector;
+use algo::parallelism::Parallelism;
+use algo::progress::{ProgressObserver, ProgressTracker};
 use rand::Rng;
+use std::fmt;
+use std::sync::Arc;
+#[cfg(feature = "parallel")]
 use rayon;
 
 use errors::*;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PermutationTestResult {
     pub p_value: f64,
     pub changepoint_index: usize,
 }
 
-fn run_algorithm_on_permutation<'a, T, B>(
+fn run_algorithm_on_permutation<T, B>(
     detector: &B,
-    true_statistic: &T,
+    true_statistic: &B::Statistic,
     permutation: &[T],
 ) -> Result<f64>
 where
-    T: Ord + Clone,
+    T: Clone,
     B: ChangePointDetector<T>,
 {
     let BestCandidate { statistic, .. } = detector.find_candidate(permutation)?;
@@ -28,77 +35,281 @@ where
     }
 }
 
-struct PermutationIteration<T: Ord + Clone> {
+/// How a permutation test spreads its permutations across threads.
+#[derive(Clone)]
+pub struct PermutationTestOptions {
+    pub parallelism: Parallelism,
+    /// The number of permutations a thread evaluates before work stops being split further.
+    pub chunk_size: usize,
+    /// Checked before every permutation; once cancelled, the test fails with
+    /// `PermutationTestCancelled`, carrying the p-value of the permutations that did complete.
+    pub cancellation: Option<CancellationToken>,
+    /// Told about every permutation as it completes.
+    pub progress: Option<Arc<dyn ProgressObserver>>,
+}
+
+impl fmt::Debug for PermutationTestOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PermutationTestOptions")
+            .field("parallelism", &self.parallelism)
+            .field("chunk_size", &self.chunk_size)
+            .field("cancellation", &self.cancellation)
+            .field("progress", &self.progress.as_ref().map(|_| "ProgressObserver"))
+            .finish()
+    }
+}
+
+impl Default for PermutationTestOptions {
+    fn default() -> Self {
+        PermutationTestOptions {
+            parallelism: Parallelism::default(),
+            chunk_size: 1,
+            cancellation: None,
+            progress: None,
+        }
+    }
+}
+
+impl PermutationTestOptions {
+    pub fn with_parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    pub fn with_progress<O: ProgressObserver + 'static>(mut self, observer: O) -> Self {
+        self.progress = Some(Arc::new(observer));
+        self
+    }
+}
+
+// What a single run of the test checks and reports to while evaluating permutations.
+#[derive(Clone, Copy)]
+struct Monitoring<'a> {
+    cancellation: Option<&'a CancellationToken>,
+    progress: Option<&'a ProgressTracker>,
+}
+
+impl<'a> Monitoring<'a> {
+    fn is_cancelled(&self) -> bool {
+        self.cancellation.is_some_and(CancellationToken::is_cancelled)
+    }
+
+    fn record(&self, greater_than_truth: &Result<f64>) {
+        if let (Some(progress), Ok(greater_than_truth)) = (self.progress, greater_than_truth) {
+            progress.record(*greater_than_truth > 0.0);
+        }
+    }
+}
+
+struct PermutationIteration<T: Clone> {
     permutation: Vec<T>,
     greater_than_truth: Option<Result<f64>>,
 }
 
+fn do_permutation_iterations_sequentially<T, B>(
+    algorithm: &B,
+    true_statistic: &B::Statistic,
+    permutation_iterations: &mut [PermutationIteration<T>],
+    monitoring: Monitoring,
+) where
+    T: Clone,
+    B: ChangePointDetector<T>,
+{
+    for permutation_iteration in permutation_iterations {
+        if monitoring.is_cancelled() {
+            return;
+        }
+        let greater_than_truth = run_algorithm_on_permutation(
+            algorithm,
+            true_statistic,
+            &permutation_iteration.permutation,
+        );
+        monitoring.record(&greater_than_truth);
+        permutation_iteration.greater_than_truth = Some(greater_than_truth);
+    }
+}
+
+#[cfg(feature = "parallel")]
 fn do_permutation_iteration<T, B>(
     algorithm: &B,
-    true_statistic: &T,
+    true_statistic: &B::Statistic,
     permutation_iterations: &mut [PermutationIteration<T>],
-) -> ()
-where
-    T: Ord + Clone + Send + Sync,
+    chunk_size: usize,
+    monitoring: Monitoring,
+) where
+    T: Clone + Send + Sync,
     B: ChangePointDetector<T> + Send + Sync,
+    B::Statistic: Send + Sync,
 {
-    if permutation_iterations.len() <= 1 {
-        for permutation_iteration in permutation_iterations {
-            permutation_iteration.greater_than_truth = Some(run_algorithm_on_permutation(
-                algorithm,
-                true_statistic,
-                &permutation_iteration.permutation,
-            ));
-        }
+    if permutation_iterations.len() <= chunk_size {
+        do_permutation_iterations_sequentially(
+            algorithm,
+            true_statistic,
+            permutation_iterations,
+            monitoring,
+        );
     } else {
         let slice_point: usize = permutation_iterations.len() / 2;
         let (left, right) = permutation_iterations.split_at_mut(slice_point);
         rayon::join(
-            || do_permutation_iteration(algorithm, true_statistic, left),
-            || do_permutation_iteration(algorithm, true_statistic, right),
+            || do_permutation_iteration(algorithm, true_statistic, left, chunk_size, monitoring),
+            || do_permutation_iteration(algorithm, true_statistic, right, chunk_size, monitoring),
         );
     }
 }
 
-pub fn permutation_test<'a, T, B, R>(
+// Sequentially, and always without the `parallel` feature such as on WebAssembly, the
+// permutations are evaluated one after another. They are drawn the same way either way, so the
+// results are identical.
+fn do_permutation_iterations<T, B>(
+    algorithm: &B,
+    true_statistic: &B::Statistic,
+    permutation_iterations: &mut [PermutationIteration<T>],
+    options: &PermutationTestOptions,
+) where
+    T: Clone + Send + Sync,
+    B: ChangePointDetector<T> + Send + Sync,
+    B::Statistic: Send + Sync,
+{
+    let progress = options.progress.as_ref().map(|observer| {
+        ProgressTracker::new(observer.clone(), permutation_iterations.len())
+    });
+    let monitoring = Monitoring {
+        cancellation: options.cancellation.as_ref(),
+        progress: progress.as_ref(),
+    };
+    #[cfg(feature = "parallel")]
+    {
+        if !options.parallelism.is_sequential() {
+            let chunk_size = options.chunk_size.max(1);
+            return options.parallelism.install(|| {
+                do_permutation_iteration(
+                    algorithm,
+                    true_statistic,
+                    permutation_iterations,
+                    chunk_size,
+                    monitoring,
+                )
+            });
+        }
+    }
+    do_permutation_iterations_sequentially(
+        algorithm,
+        true_statistic,
+        permutation_iterations,
+        monitoring,
+    );
+}
+
+pub fn permutation_test<T, B, R>(
+    algorithm: &B,
+    rng: R,
+    num_permutations: usize,
+    observations: &[T],
+) -> Result<PermutationTestResult>
+where
+    T: Clone + Send + Sync,
+    B: ChangePointDetector<T> + Send + Sync,
+    B::Statistic: Send + Sync,
+    R: Rng,
+{
+    permutation_test_with_options(
+        algorithm,
+        rng,
+        num_permutations,
+        observations,
+        &PermutationTestOptions::default(),
+    )
+}
+
+/// `permutation_test` run on the thread pool and with the chunk size given by `options`.
+///
+/// The permutations are drawn before any of them is evaluated, so the result does not depend on
+/// the options.
+pub fn permutation_test_with_options<T, B, R>(
     algorithm: &B,
     mut rng: R,
     num_permutations: usize,
-    observations: &'a [T],
+    observations: &[T],
+    options: &PermutationTestOptions,
 ) -> Result<PermutationTestResult>
 where
-    T: Ord + Clone + Send + Sync,
+    T: Clone + Send + Sync,
     B: ChangePointDetector<T> + Send + Sync,
+    B::Statistic: Send + Sync,
     R: Rng,
+{
+    let candidate = algorithm.find_candidate(observations)?;
+    let permutations = (0..num_permutations)
+        .map(|_| {
+            let mut permutation = observations.to_vec();
+            rng.shuffle(&mut permutation);
+            permutation
+        })
+        .collect();
+    test_against_resamples(algorithm, candidate, permutations, options)
+}
+
+// Compares the statistic of `candidate` against that of every resampled series, such as the
+// permutations of the observations, counting how often it is exceeded.
+pub(crate) fn test_against_resamples<T, B>(
+    algorithm: &B,
+    candidate: BestCandidate<B::Statistic>,
+    resamples: Vec<Vec<T>>,
+    options: &PermutationTestOptions,
+) -> Result<PermutationTestResult>
+where
+    T: Clone + Send + Sync,
+    B: ChangePointDetector<T> + Send + Sync,
+    B::Statistic: Send + Sync,
 {
     let BestCandidate {
         statistic: true_statistic,
         location: true_location,
-    } = algorithm.find_candidate(observations)?;
-    let mut permutations: Vec<PermutationIteration<T>> = Vec::new();
-    for _ in 0..num_permutations {
-        let mut inner_vec = observations.to_vec();
-        rng.shuffle(&mut inner_vec);
-        let permutation_iteration = PermutationIteration {
-            permutation: inner_vec,
-            greater_than_truth: None,
-        };
-        permutations.push(permutation_iteration);
-    }
-    do_permutation_iteration(algorithm, &true_statistic, &mut permutations);
-    let num_failures: Result<f64> = permutations.into_iter().fold(Ok(0.0), |num_failures,
-     permutation| {
-        Ok(
-            num_failures? +
-                match permutation.greater_than_truth {
-                    Some(result) => result?,
-                    None => return Err(ErrorKind::PermutationNeverRan.into()),
-                },
-        )
-    });
-    let p_value = num_failures? / ((num_permutations + 1) as f64);
+    } = candidate;
+    let num_permutations = resamples.len();
+    let mut permutations: Vec<PermutationIteration<T>> = resamples
+        .into_iter()
+        .map(|permutation| {
+            PermutationIteration {
+                permutation,
+                greater_than_truth: None,
+            }
+        })
+        .collect();
+    do_permutation_iterations(algorithm, &true_statistic, &mut permutations, options);
+    let mut num_completed = 0;
+    let mut num_failures = 0.0;
+    for permutation in permutations {
+        match permutation.greater_than_truth {
+            Some(Ok(failure)) => {
+                num_completed += 1;
+                num_failures += failure;
+            }
+            Some(Err(Error(ErrorKind::Cancelled, _))) => {}
+            Some(Err(error)) => return Err(error),
+            None if options.cancellation.is_some() => {}
+            None => return Err(ErrorKind::PermutationNeverRan.into()),
+        }
+    }
+    let p_value = num_failures / ((num_completed + 1) as f64);
+    if num_completed < num_permutations {
+        return Err(
+            ErrorKind::PermutationTestCancelled(num_completed, num_permutations, p_value).into(),
+        );
+    }
     Ok(PermutationTestResult {
-        p_value: p_value,
+        p_value,
         changepoint_index: true_location,
     })
 }
@@ -108,9 +319,12 @@ mod tests {
     use super::*;
     use mersenne_twister::MersenneTwister;
     use rand::SeedableRng;
-    use rand::distributions::{IndependentSample, Normal};
     use algo::non_nan::NonNaN;
     use algo::edm_x::edm_x::EDMX;
+    use algo::progress::Progress;
+    use std::sync::Mutex;
+    use std::time::Instant;
+    use generator::normal_series;
 
     const NUM_PERMUTATIONS: usize = 10;
     // The paper recommends 199, but that takes way too long unless you compile with the
@@ -120,21 +334,9 @@ mod tests {
     #[test]
     fn edm_x_permutation_test_detects_if_change_occurred() {
         let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
-        let before_change_dist = Normal::new(10.0, 5.0);
-        let after_change_dist = Normal::new(20.0, 5.0);
-        let num_before_observations = 500;
-        let num_after_observations = 200;
         let delta = 30;
         let num_permutations = NUM_PERMUTATIONS;
-        let mut inputs: Vec<NonNaN<f64>> = Vec::new();
-        for i in 0..(num_before_observations + num_after_observations) {
-            let dist = if i < num_before_observations {
-                before_change_dist
-            } else {
-                after_change_dist
-            };
-            inputs.push(NonNaN::new(dist.ind_sample(&mut rng)).unwrap());
-        }
+        let inputs = normal_series(&mut rng, &[(500, 10.0, 5.0), (200, 20.0, 5.0)]);
         let algorithm = EDMX::new(delta);
         let full_test = permutation_test(&algorithm, rng, num_permutations, &inputs).unwrap();
         assert!(full_test.p_value <= 0.1);
@@ -143,16 +345,85 @@ mod tests {
     #[test]
     fn edm_x_permutation_test_detects_no_change_occurred() {
         let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
-        let dist = Normal::new(10.0, 5.0);
-        let num_observations = 700;
         let delta = 30;
         let num_permutations = NUM_PERMUTATIONS;
-        let mut inputs: Vec<NonNaN<f64>> = Vec::new();
-        for _ in 0..num_observations {
-            inputs.push(NonNaN::new(dist.ind_sample(&mut rng)).unwrap());
-        }
+        let inputs = normal_series(&mut rng, &[(700, 10.0, 5.0)]);
         let algorithm = EDMX::new(delta);
         let full_test = permutation_test(&algorithm, rng, num_permutations, &inputs).unwrap();
         assert!(full_test.p_value > 0.1);
     }
+
+    #[test]
+    fn permutation_test_options_do_not_change_the_result() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let inputs = normal_series(&mut rng, &[(200, 10.0, 5.0)]);
+        let algorithm = EDMX::new(20);
+        let p_value = |options: PermutationTestOptions| {
+            let rng: MersenneTwister = SeedableRng::from_seed(0x5678);
+            permutation_test_with_options(&algorithm, rng, NUM_PERMUTATIONS, &inputs, &options)
+                .unwrap()
+                .p_value
+        };
+        let default = p_value(PermutationTestOptions::default());
+        assert_eq!(
+            p_value(PermutationTestOptions::default().with_parallelism(Parallelism::Sequential)),
+            default
+        );
+        assert_eq!(
+            p_value(PermutationTestOptions::default().with_chunk_size(4)),
+            default
+        );
+        #[cfg(feature = "parallel")]
+        {
+            assert_eq!(
+                p_value(PermutationTestOptions::default().with_parallelism(
+                    Parallelism::dedicated_pool(2).unwrap(),
+                )),
+                default
+            );
+            assert!(Parallelism::dedicated_pool(0).is_err());
+        }
+    }
+
+    #[test]
+    fn cancelled_permutation_test_reports_completed_permutations() {
+        let inputs: Vec<NonNaN<f64>> = (0..100).map(|i| NonNaN::new(i as f64).unwrap()).collect();
+        let rng: MersenneTwister = SeedableRng::from_seed(0x5678);
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let options = PermutationTestOptions::default().with_cancellation(cancellation);
+        let error = permutation_test_with_options(&EDMX::new(10), rng, 9, &inputs, &options)
+            .unwrap_err();
+        match *error.kind() {
+            ErrorKind::PermutationTestCancelled(0, 9, p_value) => assert_eq!(p_value, 0.0),
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+
+        // A cancelled detector already fails on the observations themselves.
+        let rng: MersenneTwister = SeedableRng::from_seed(0x5678);
+        let cancellation = CancellationToken::new().with_deadline(Instant::now());
+        let detector = EDMX::new(10).with_cancellation(cancellation);
+        match *permutation_test(&detector, rng, 9, &inputs).unwrap_err().kind() {
+            ErrorKind::Cancelled => {}
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn progress_is_reported_in_order_for_every_permutation() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let inputs = normal_series(&mut rng, &[(200, 10.0, 5.0)]);
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let observed = reports.clone();
+        let options = PermutationTestOptions::default().with_progress(move |progress: Progress| {
+            observed.lock().unwrap().push(progress)
+        });
+        let result = permutation_test_with_options(&EDMX::new(20), rng, 19, &inputs, &options)
+            .unwrap();
+        let reports = reports.lock().unwrap();
+        let completed: Vec<usize> = reports.iter().map(|progress| progress.completed).collect();
+        assert_eq!(completed, (1..20).collect::<Vec<_>>());
+        assert!(reports.iter().all(|progress| progress.total == 19));
+        assert_eq!(reports[18].provisional_p_value(), result.p_value);
+    }
 }
@@ -0,0 +1,62 @@
+use std::sync::{Arc, Mutex};
+
+/// How far a permutation test has got.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    pub completed: usize,
+    pub total: usize,
+    /// The completed permutations whose statistic exceeded that of the observations.
+    pub exceedances: usize,
+}
+
+impl Progress {
+    /// The p-value the test would report if it stopped now.
+    pub fn provisional_p_value(&self) -> f64 {
+        self.exceedances as f64 / ((self.completed + 1) as f64)
+    }
+}
+
+/// Receives the progress of a permutation test each time a permutation completes.
+///
+/// Reports are made one at a time and in order, even when permutations run on several threads,
+/// so observers should return quickly. Closures taking a `Progress` are observers.
+pub trait ProgressObserver: Send + Sync {
+    fn on_progress(&self, progress: Progress);
+}
+
+impl<F: Fn(Progress) + Send + Sync> ProgressObserver for F {
+    fn on_progress(&self, progress: Progress) {
+        self(progress)
+    }
+}
+
+// Counts the permutations of one test, reporting to the observer under the lock so that the
+// reports stay consistent and ordered.
+pub(crate) struct ProgressTracker {
+    observer: Arc<dyn ProgressObserver>,
+    progress: Mutex<Progress>,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(observer: Arc<dyn ProgressObserver>, total: usize) -> Self {
+        ProgressTracker {
+            observer,
+            progress: Mutex::new(Progress {
+                completed: 0,
+                total,
+                exceedances: 0,
+            }),
+        }
+    }
+
+    pub(crate) fn record(&self, exceeded: bool) {
+        let mut progress = self.progress.lock().unwrap_or_else(|poisoned| {
+            poisoned.into_inner()
+        });
+        progress.completed += 1;
+        if exceeded {
+            progress.exceedances += 1;
+        }
+        self.observer.on_progress(*progress);
+    }
+}
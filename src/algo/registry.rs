@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::iter::FromIterator;
+use std::str::FromStr;
+use algo::changepoint::ChangePointDetector;
+use algo::dispersion::{Dispersion, EDMScale};
+use algo::edm_x::edm_x::EDMX;
+use algo::kcp::{KCP, Kernel, ModelSelection};
+use algo::non_nan::NonNaN;
+use algo::trend::{Continuity, TrendDetector};
+
+use errors::*;
+
+/// A detector of changes in a univariate series, chosen at runtime.
+pub type BoxedDetector = Box<
+    dyn ChangePointDetector<NonNaN<f64>, Statistic = NonNaN<f64>> + Send + Sync,
+>;
+
+/// The parameters a detector is built from, by name, with values as written in a configuration
+/// file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Parameters {
+    values: BTreeMap<String, String>,
+}
+
+impl Parameters {
+    pub fn new() -> Self {
+        Parameters::default()
+    }
+
+    pub fn with<V: ToString>(mut self, name: &str, value: V) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// The value of `name` parsed as a `V`, or `None` when it is not given.
+    pub fn get<V>(&self, name: &str) -> Result<Option<V>>
+    where
+        V: FromStr,
+        V::Err: Display,
+    {
+        self.values
+            .get(name)
+            .map(|value| parse(name, value))
+            .transpose()
+    }
+
+    /// The value of `name` parsed as a `V`, or `default` when it is not given.
+    pub fn get_or<V>(&self, name: &str, default: V) -> Result<V>
+    where
+        V: FromStr,
+        V::Err: Display,
+    {
+        Ok(self.get(name)?.unwrap_or(default))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.values.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+impl<K: Into<String>, V: ToString> FromIterator<(K, V)> for Parameters {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iterator: I) -> Self {
+        Parameters {
+            values: iterator
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+type Factory = Box<dyn Fn(&Parameters) -> Result<BoxedDetector> + Send + Sync>;
+
+struct Entry {
+    parameters: Vec<String>,
+    factory: Factory,
+}
+
+/// Builds detectors from a name and `Parameters`, such as `edmx` with `delta=30`.
+///
+/// The built-in detectors take the names and parameters they report through
+/// `ChangePointDetector::name` and `ChangePointDetector::parameters`, so a detector can be
+/// rebuilt from its description.
+pub struct DetectorRegistry {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Default for DetectorRegistry {
+    fn default() -> Self {
+        DetectorRegistry::new()
+    }
+}
+
+impl DetectorRegistry {
+    /// A registry of the built-in univariate detectors.
+    pub fn new() -> Self {
+        let mut registry = DetectorRegistry::empty();
+        registry.register("edmx", &["delta"], |parameters| {
+            Ok(Box::new(EDMX::new(positive(parameters, "delta", 30)?)))
+        });
+        registry.register("edm-mad", &["delta"], |parameters| {
+            let delta = positive(parameters, "delta", 30)?;
+            Ok(Box::new(
+                EDMScale::new(delta, Dispersion::MedianAbsoluteDeviation),
+            ))
+        });
+        registry.register("edm-iqr", &["delta"], |parameters| {
+            let delta = positive(parameters, "delta", 30)?;
+            Ok(Box::new(EDMScale::new(delta, Dispersion::InterquartileRange)))
+        });
+        registry.register(
+            "kcp",
+            &[
+                "kernel",
+                "bandwidth",
+                "min_segment_length",
+                "max_changepoints",
+                "model_selection",
+            ],
+            build_kcp,
+        );
+        registry.register(
+            "trend",
+            &["min_segment_length", "continuity", "penalty"],
+            build_trend,
+        );
+        registry
+    }
+
+    pub fn empty() -> Self {
+        DetectorRegistry { entries: BTreeMap::new() }
+    }
+
+    /// Adds or replaces the detector called `name`, accepting only the given parameters.
+    pub fn register<F>(&mut self, name: &str, parameters: &[&str], factory: F)
+    where
+        F: Fn(&Parameters) -> Result<BoxedDetector> + Send + Sync + 'static,
+    {
+        self.entries.insert(
+            name.to_string(),
+            Entry {
+                parameters: parameters.iter().map(|name| name.to_string()).collect(),
+                factory: Box::new(factory),
+            },
+        );
+    }
+
+    /// The names of the registered detectors, in alphabetical order.
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.keys().map(|name| name.as_str()).collect()
+    }
+
+    pub fn build(&self, name: &str, parameters: &Parameters) -> Result<BoxedDetector> {
+        let entry = self.entries.get(name).ok_or_else(|| {
+            Error::from(ErrorKind::UnknownDetector(
+                name.to_string(),
+                self.names().join(", "),
+            ))
+        })?;
+        if let Some(unknown) = parameters.names().into_iter().find(|parameter| {
+            !entry.parameters.iter().any(|accepted| accepted == parameter)
+        })
+        {
+            return Err(
+                ErrorKind::InvalidParameter(
+                    unknown.to_string(),
+                    format!(
+                        "{} does not take it, only {}",
+                        name,
+                        entry.parameters.join(", ")
+                    ),
+                ).into(),
+            );
+        }
+        (entry.factory)(parameters)
+    }
+}
+
+fn parse<V>(name: &str, value: &str) -> Result<V>
+where
+    V: FromStr,
+    V::Err: Display,
+{
+    value.parse().map_err(|error: V::Err| {
+        ErrorKind::InvalidParameter(
+            name.to_string(),
+            format!("{:?} could not be parsed: {}", value, error),
+        ).into()
+    })
+}
+
+fn positive(parameters: &Parameters, name: &str, default: usize) -> Result<usize> {
+    let value = parameters.get_or(name, default)?;
+    if value == 0 {
+        Err(
+            ErrorKind::InvalidParameter(name.to_string(), "must be at least 1".to_string()).into(),
+        )
+    } else {
+        Ok(value)
+    }
+}
+
+fn invalid_choice(name: &str, value: &str, expected: &str) -> Error {
+    ErrorKind::InvalidParameter(
+        name.to_string(),
+        format!("{:?} is not one of {}", value, expected),
+    ).into()
+}
+
+fn build_kcp(parameters: &Parameters) -> Result<BoxedDetector> {
+    let kernel = match parameters.get_or("kernel", "gaussian".to_string())?.as_str() {
+        "gaussian" => Kernel::Gaussian(parameters.get("bandwidth")?),
+        "linear" if parameters.get::<f64>("bandwidth")?.is_some() => {
+            return Err(
+                ErrorKind::InvalidParameter(
+                    "bandwidth".to_string(),
+                    "only the gaussian kernel takes it".to_string(),
+                ).into(),
+            )
+        }
+        "linear" => Kernel::Linear,
+        other => return Err(invalid_choice("kernel", other, "gaussian, linear")),
+    };
+    let model_selection: String = parameters.get_or(
+        "model_selection",
+        "slope_heuristic".to_string(),
+    )?;
+    let invalid_model_selection = || {
+        invalid_choice(
+            "model_selection",
+            &model_selection,
+            "slope_heuristic, penalty:<constant>, fixed:<count>",
+        )
+    };
+    let model_selection = match model_selection.find(':') {
+        None if model_selection == "slope_heuristic" => ModelSelection::SlopeHeuristic,
+        Some(colon) => {
+            match (&model_selection[..colon], &model_selection[colon + 1..]) {
+                ("penalty", constant) => {
+                    ModelSelection::Penalty(parse("model_selection", constant)?)
+                }
+                ("fixed", count) => ModelSelection::Fixed(parse("model_selection", count)?),
+                _ => return Err(invalid_model_selection()),
+            }
+        }
+        None => return Err(invalid_model_selection()),
+    };
+    let detector = KCP::new(
+        kernel,
+        positive(parameters, "min_segment_length", 30)?,
+        parameters.get_or("max_changepoints", 1)?,
+    ).with_model_selection(model_selection);
+    detector.check_parameters()?;
+    Ok(Box::new(detector))
+}
+
+fn build_trend(parameters: &Parameters) -> Result<BoxedDetector> {
+    let continuity = match parameters
+        .get_or("continuity", "discontinuous".to_string())?
+        .as_str() {
+        "continuous" => Continuity::Continuous,
+        "discontinuous" => Continuity::Discontinuous,
+        other => {
+            return Err(invalid_choice(
+                "continuity",
+                other,
+                "continuous, discontinuous",
+            ))
+        }
+    };
+    let detector = TrendDetector::new(positive(parameters, "min_segment_length", 30)?, continuity);
+    let detector = match parameters.get("penalty")? {
+        Some(penalty) => detector.with_penalty(penalty),
+        None => detector,
+    };
+    detector.check_parameters()?;
+    Ok(Box::new(detector))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::permutation_test::permutation_test;
+    use mersenne_twister::MersenneTwister;
+    use rand::SeedableRng;
+
+    #[test]
+    fn builds_detectors_that_describe_themselves_the_same_way() {
+        let registry = DetectorRegistry::new();
+        let edmx = registry.build("edmx", &Parameters::new().with("delta", 10)).unwrap();
+        assert_eq!(edmx.describe(), "edmx(delta=10)");
+        let observations: Vec<NonNaN<f64>> = (0..60)
+            .map(|i| NonNaN::new(if i < 30 { 0.0 } else { 10.0 } + (i % 3) as f64).unwrap())
+            .collect();
+        let rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let result = permutation_test(&edmx, rng, 9, &observations).unwrap();
+        assert!((result.changepoint_index as i64 - 30).abs() <= 2);
+
+        for name in registry.names() {
+            let detector = registry.build(name, &Parameters::new()).unwrap();
+            let rebuilt = registry
+                .build(&detector.name(), &detector.parameters().into_iter().collect())
+                .unwrap();
+            assert_eq!(rebuilt.describe(), detector.describe());
+        }
+        let kcp = registry
+            .build(
+                "kcp",
+                &vec![("kernel", "linear"), ("model_selection", "penalty:2.5")]
+                    .into_iter()
+                    .collect(),
+            )
+            .unwrap();
+        assert_eq!(
+            kcp.describe(),
+            "kcp(kernel=linear, min_segment_length=30, max_changepoints=1, \
+             model_selection=penalty:2.5)"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_detectors_and_bad_parameters() {
+        let registry = DetectorRegistry::new();
+        let error = |name: &str, parameters: Parameters| {
+            registry.build(name, &parameters).err().unwrap().to_string()
+        };
+        assert_eq!(
+            error("edm", Parameters::new()),
+            "Unknown detector \"edm\", expected one of edm-iqr, edm-mad, edmx, kcp, trend"
+        );
+        assert!(error("edmx", Parameters::new().with("delta", "ten")).contains("delta"));
+        assert!(error("edmx", Parameters::new().with("delta", 0)).contains("at least 1"));
+        assert!(error("edmx", Parameters::new().with("kernel", "linear")).contains("kernel"));
+        assert!(error("trend", Parameters::new().with("continuity", "smooth")).contains("smooth"));
+        assert!(
+            error("kcp", Parameters::new().with("model_selection", "fixed")).contains("fixed")
+        );
+        for bandwidth in &["0", "-1", "NaN", "inf"] {
+            let message = error("kcp", Parameters::new().with("bandwidth", bandwidth));
+            assert!(message.contains("bandwidth"), "{}", message);
+        }
+        let linear = Parameters::new().with("kernel", "linear").with("bandwidth", 1.5);
+        assert!(error("kcp", linear).contains("only the gaussian kernel"));
+        let penalty = Parameters::new().with("model_selection", "penalty:-1");
+        assert!(error("kcp", penalty).contains("model_selection"));
+        for penalty in &["NaN", "-0.5"] {
+            let message = error("trend", Parameters::new().with("penalty", penalty));
+            assert!(message.contains("penalty"), "{}", message);
+        }
+        let short = Parameters::new().with("min_segment_length", 1);
+        assert!(error("trend", short).contains("at least 2"));
+    }
+}
@@ -0,0 +1,302 @@
+use num::Float;
+use std::convert::TryFrom;
+use algo::non_nan::NonNaN;
+use algo::stats::{from_f64, median};
+use algo::time_series::TimeSeries;
+
+use errors::*;
+
+/// How the observations falling in one interval are summarised.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Aggregator {
+    Median,
+    Mean,
+    /// The quantile at the given probability, e.g. `Quantile(0.95)` for the p95, interpolating
+    /// linearly between order statistics.
+    Quantile(f64),
+    Count,
+    Sum,
+}
+
+/// What to do with intervals containing no finite observations. `Count` and `Sum` never see
+/// gaps, as an empty interval simply counts or sums to zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum GapPolicy {
+    /// Leave the interval out, so the result is no longer evenly spaced.
+    Skip,
+    Fill(f64),
+    ForwardFill,
+    /// Interpolate linearly between the nearest non-empty intervals on either side.
+    Interpolate,
+    Error,
+}
+
+/// The most intervals `resample` produces, so that two events far apart with a short interval
+/// fail instead of allocating a bucket for every interval in between.
+pub const MAX_RESAMPLED_INTERVALS: usize = 1_000_000;
+
+fn quantile(mut values: Vec<f64>, probability: f64) -> f64 {
+    values.sort_by(|a, b| a.partial_cmp(b).expect("Finite values are never NaN"));
+    let position = probability * (values.len() - 1) as f64;
+    let below = position.floor() as usize;
+    let above = position.ceil() as usize;
+    values[below] + (values[above] - values[below]) * (position - below as f64)
+}
+
+impl Aggregator {
+    fn check(&self) -> Result<()> {
+        match *self {
+            Aggregator::Quantile(probability) if !(0.0..=1.0).contains(&probability) => {
+                Err(
+                    ErrorKind::InvalidParameter(
+                        "quantile".to_string(),
+                        format!("{} is not in [0, 1]", probability),
+                    ).into(),
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn aggregate(&self, values: Vec<f64>) -> Option<f64> {
+        match *self {
+            Aggregator::Count => Some(values.len() as f64),
+            Aggregator::Sum => Some(values.iter().sum()),
+            _ if values.is_empty() => None,
+            Aggregator::Median => Some(median(values)),
+            Aggregator::Mean => Some(values.iter().sum::<f64>() / values.len() as f64),
+            Aggregator::Quantile(probability) => Some(quantile(values, probability)),
+        }
+    }
+}
+
+fn fill_gaps(
+    starts: &[i64],
+    aggregates: Vec<Option<f64>>,
+    gaps: GapPolicy,
+) -> Result<(Vec<i64>, Vec<f64>)> {
+    let mut timestamps = Vec::with_capacity(starts.len());
+    let mut values = Vec::with_capacity(starts.len());
+    for (position, aggregate) in aggregates.iter().enumerate() {
+        let value = match (*aggregate, gaps) {
+            (Some(value), _) => value,
+            (None, GapPolicy::Skip) => continue,
+            (None, GapPolicy::Fill(fill)) => fill,
+            (None, GapPolicy::Error) => {
+                return Err(ErrorKind::EmptyBucket(starts[position]).into());
+            }
+            // The first and last intervals always contain observations, so there is always a
+            // non-empty interval on either side of a gap.
+            (None, GapPolicy::ForwardFill) => {
+                *values.last().expect(
+                    "The first interval is never empty",
+                )
+            }
+            (None, GapPolicy::Interpolate) => {
+                let previous = (0..position)
+                    .rev()
+                    .find(|&p| aggregates[p].is_some())
+                    .expect("The first interval is never empty");
+                let next = ((position + 1)..aggregates.len())
+                    .find(|&p| aggregates[p].is_some())
+                    .expect("The last interval is never empty");
+                let previous_value = aggregates[previous].expect("found by is_some");
+                let next_value = aggregates[next].expect("found by is_some");
+                let fraction = (position - previous) as f64 / (next - previous) as f64;
+                previous_value + (next_value - previous_value) * fraction
+            }
+        };
+        timestamps.push(starts[position]);
+        values.push(value);
+    }
+    Ok((timestamps, values))
+}
+
+/// Buckets a raw series into intervals of `interval` timestamp units aligned to multiples of
+/// `interval`, and summarises every interval with the `Aggregator`. Each output timestamp is the
+/// start of its interval. Non-finite raw values are ignored, so an interval holding nothing but
+/// NaNs counts as a gap.
+///
+/// A `TimeSeries` holds at most one value per timestamp, so use `resample_events` to count or sum
+/// events that can share a timestamp.
+pub fn resample<F: Float>(
+    series: &TimeSeries<F>,
+    interval: i64,
+    aggregator: Aggregator,
+    gaps: GapPolicy,
+) -> Result<TimeSeries<NonNaN<F>>> {
+    let events: Vec<(i64, F)> = series
+        .timestamps()
+        .iter()
+        .cloned()
+        .zip(series.values().iter().cloned())
+        .collect();
+    resample_events(&events, interval, aggregator, gaps)
+}
+
+/// `resample` on raw `(timestamp, value)` events, which may share timestamps and come in any
+/// order.
+pub fn resample_events<F: Float>(
+    events: &[(i64, F)],
+    interval: i64,
+    aggregator: Aggregator,
+    gaps: GapPolicy,
+) -> Result<TimeSeries<NonNaN<F>>> {
+    if interval <= 0 {
+        return Err(
+            ErrorKind::InvalidParameter(
+                "interval".to_string(),
+                format!("{} is not positive", interval),
+            ).into(),
+        );
+    }
+    aggregator.check()?;
+    if let GapPolicy::Fill(fill) = gaps {
+        if !fill.is_finite() {
+            return Err(
+                ErrorKind::InvalidParameter("fill".to_string(), format!("{} is not finite", fill))
+                    .into(),
+            );
+        }
+    }
+    let finite: Vec<(i64, f64)> = events
+        .iter()
+        .filter_map(|&(timestamp, value)| {
+            NonNaN::new(value).map(|value| {
+                (
+                    timestamp,
+                    value.value().to_f64().expect(
+                        "Finite floating point values always convert to f64",
+                    ),
+                )
+            })
+        })
+        .collect();
+    let buckets_of = finite.iter().map(|&(timestamp, _)| timestamp.div_euclid(interval));
+    let (first, last) = match (buckets_of.clone().min(), buckets_of.max()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return TimeSeries::new(Vec::new(), Vec::new()),
+    };
+    let num_buckets = last.checked_sub(first)
+        .and_then(|span| usize::try_from(span).ok())
+        .and_then(|span| span.checked_add(1))
+        .filter(|&num_buckets| num_buckets <= MAX_RESAMPLED_INTERVALS)
+        .ok_or_else(|| {
+            Error::from(ErrorKind::InvalidParameter(
+                "interval".to_string(),
+                format!(
+                    "{} splits the events into more than {} intervals",
+                    interval,
+                    MAX_RESAMPLED_INTERVALS
+                ),
+            ))
+        })?;
+    let mut buckets: Vec<Vec<f64>> = vec![Vec::new(); num_buckets];
+    for (timestamp, value) in finite {
+        buckets[(timestamp.div_euclid(interval) - first) as usize].push(value);
+    }
+    let starts: Vec<i64> = (0..num_buckets)
+        .map(|bucket| (first + bucket as i64) * interval)
+        .collect();
+    let aggregates = buckets
+        .into_iter()
+        .map(|values| aggregator.aggregate(values))
+        .collect();
+    let (timestamps, values) = fill_gaps(&starts, aggregates, gaps)?;
+    TimeSeries::new(timestamps, values.into_iter().map(from_f64).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_series() -> TimeSeries<f64> {
+        // Three events in [0, 10), none in [10, 20), one NaN and two events in [20, 30).
+        TimeSeries::new(
+            vec![1, 4, 9, 21, 23, 28],
+            vec![3.0, 1.0, 2.0, f64::NAN, 10.0, 20.0],
+        ).unwrap()
+    }
+
+    fn resampled_values(aggregator: Aggregator, gaps: GapPolicy) -> Vec<f64> {
+        resample(&raw_series(), 10, aggregator, gaps)
+            .unwrap()
+            .values()
+            .iter()
+            .map(|value| value.value())
+            .collect()
+    }
+
+    #[test]
+    fn aggregators_summarise_each_interval() {
+        assert_eq!(
+            resampled_values(Aggregator::Median, GapPolicy::Fill(0.0)),
+            vec![2.0, 0.0, 15.0]
+        );
+        assert_eq!(
+            resampled_values(Aggregator::Mean, GapPolicy::Fill(0.0)),
+            vec![2.0, 0.0, 15.0]
+        );
+        assert_eq!(
+            resampled_values(Aggregator::Quantile(0.95), GapPolicy::Fill(0.0)),
+            vec![2.9, 0.0, 19.5]
+        );
+        assert_eq!(
+            resampled_values(Aggregator::Count, GapPolicy::Error),
+            vec![3.0, 0.0, 2.0]
+        );
+        assert_eq!(
+            resampled_values(Aggregator::Sum, GapPolicy::Error),
+            vec![6.0, 0.0, 30.0]
+        );
+        let resampled = resample(&raw_series(), 10, Aggregator::Median, GapPolicy::Skip).unwrap();
+        assert_eq!(resampled.timestamps(), &[0, 20]);
+    }
+
+    #[test]
+    fn gap_policies_fill_empty_intervals() {
+        assert_eq!(
+            resampled_values(Aggregator::Median, GapPolicy::ForwardFill),
+            vec![2.0, 2.0, 15.0]
+        );
+        assert_eq!(
+            resampled_values(Aggregator::Median, GapPolicy::Interpolate),
+            vec![2.0, 8.5, 15.0]
+        );
+        match *resample(&raw_series(), 10, Aggregator::Median, GapPolicy::Error)
+            .unwrap_err()
+            .kind() {
+            ErrorKind::EmptyBucket(10) => {}
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+        assert!(resample(&raw_series(), 0, Aggregator::Median, GapPolicy::Skip).is_err());
+    }
+
+    #[test]
+    fn events_sharing_timestamps_are_all_aggregated() {
+        let events = vec![(12, 1.0), (3, 1.0), (3, 2.0), (3, 4.0), (17, f64::NAN), (12, 1.0)];
+        let counts = resample_events(&events, 10, Aggregator::Count, GapPolicy::Error).unwrap();
+        assert_eq!(counts.timestamps(), &[0, 10]);
+        let counts: Vec<f64> = counts.values().iter().map(|value| value.value()).collect();
+        assert_eq!(counts, vec![3.0, 2.0]);
+        let sums = resample_events(&events, 10, Aggregator::Sum, GapPolicy::Error).unwrap();
+        let sums: Vec<f64> = sums.values().iter().map(|value| value.value()).collect();
+        assert_eq!(sums, vec![7.0, 2.0]);
+
+        let far_apart = vec![(0, 1.0), (i64::MAX, 1.0)];
+        for &interval in &[1, 1000] {
+            match *resample_events(&far_apart, interval, Aggregator::Count, GapPolicy::Error)
+                .unwrap_err()
+                .kind() {
+                ErrorKind::InvalidParameter(ref name, _) => assert_eq!(name, "interval"),
+                ref other => panic!("Unexpected error {:?}", other),
+            }
+        }
+        let spread = vec![(i64::MIN, 1.0), (i64::MAX, 1.0)];
+        assert!(resample_events(&spread, 1, Aggregator::Count, GapPolicy::Error).is_err());
+    }
+}
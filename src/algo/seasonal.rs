@@ -0,0 +1,566 @@
+use num::Float;
+use rand::Rng;
+use algo::best_candidate::BestCandidate;
+use algo::changepoint::ChangePointDetector;
+use algo::non_nan::NonNaN;
+use algo::permutation_test::{PermutationTestOptions, PermutationTestResult,
+                             permutation_test_with_options};
+use algo::stats::{from_f64, median, to_f64s};
+
+use errors::*;
+
+/// The additive components of a series: `values = trend + seasonal + remainder`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Decomposition {
+    pub trend: Vec<f64>,
+    pub seasonal: Vec<f64>,
+    pub remainder: Vec<f64>,
+}
+
+fn next_odd(value: usize) -> usize {
+    if value.is_multiple_of(2) { value + 1 } else { value }
+}
+
+fn tricube(distance: f64) -> f64 {
+    let cubed = 1.0 - distance * distance * distance;
+    cubed * cubed * cubed
+}
+
+// Locally linear regression evaluated at position `at`, where `values[j]` sits at position `j`.
+// The `span` nearest positions are weighted by a tricube kernel and, during the robust passes of
+// STL, by the robustness weights. Returns `None` when every weight vanishes.
+fn loess(values: &[f64], robustness: Option<&[f64]>, span: usize, at: f64) -> Option<f64> {
+    let n = values.len();
+    let (left, right) = if span >= n {
+        (0, n - 1)
+    } else {
+        let ideal_left = (at - ((span - 1) / 2) as f64).round().max(0.0) as usize;
+        let left = ideal_left.min(n - span);
+        (left, left + span - 1)
+    };
+    let mut h = (at - left as f64).max(right as f64 - at);
+    if span > n {
+        h += ((span - n) / 2) as f64;
+    }
+    let mut weights: Vec<f64> = (left..(right + 1))
+        .map(|j| {
+            let distance = (j as f64 - at).abs();
+            let weight = if distance <= 0.001 * h {
+                1.0
+            } else if distance <= 0.999 * h {
+                tricube(distance / h)
+            } else {
+                0.0
+            };
+            weight * robustness.map(|r| r[j]).unwrap_or(1.0)
+        })
+        .collect();
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+    for weight in &mut weights {
+        *weight /= total;
+    }
+    let center: f64 = weights
+        .iter()
+        .enumerate()
+        .map(|(offset, weight)| weight * (left + offset) as f64)
+        .sum();
+    let spread: f64 = weights
+        .iter()
+        .enumerate()
+        .map(|(offset, weight)| {
+            let deviation = (left + offset) as f64 - center;
+            weight * deviation * deviation
+        })
+        .sum();
+    if spread.sqrt() > 0.001 * (n - 1) as f64 {
+        let slope = (at - center) / spread;
+        for (offset, weight) in weights.iter_mut().enumerate() {
+            *weight *= 1.0 + slope * ((left + offset) as f64 - center);
+        }
+    }
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .map(|(offset, weight)| weight * values[left + offset])
+            .sum(),
+    )
+}
+
+fn loess_smooth(values: &[f64], robustness: Option<&[f64]>, span: usize) -> Vec<f64> {
+    (0..values.len())
+        .map(|index| {
+            loess(values, robustness, span, index as f64).unwrap_or(values[index])
+        })
+        .collect()
+}
+
+fn moving_average(values: &[f64], window: usize) -> Vec<f64> {
+    values
+        .windows(window)
+        .map(|window_values| window_values.iter().sum::<f64>() / window as f64)
+        .collect()
+}
+
+/// Seasonal-trend decomposition by loess (Cleveland et al., 1990).
+///
+/// Without a seasonal span the seasonal component is periodic, i.e. the same for every cycle;
+/// with one, each cycle-subseries is smoothed by loess over that many cycles so the seasonal
+/// pattern may drift. The robust variant downweights outliers so they end up in the remainder.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Stl {
+    period: usize,
+    seasonal_span: Option<usize>,
+    robust: bool,
+}
+
+impl Stl {
+    pub fn new(period: usize) -> Self {
+        Stl {
+            period,
+            seasonal_span: None,
+            robust: true,
+        }
+    }
+
+    pub fn with_seasonal_span(mut self, seasonal_span: usize) -> Self {
+        self.seasonal_span = Some(next_odd(seasonal_span.max(3)));
+        self
+    }
+
+    pub fn with_robustness(mut self, robust: bool) -> Self {
+        self.robust = robust;
+        self
+    }
+
+    pub fn decompose<F: Float>(&self, observations: &[NonNaN<F>]) -> Result<Decomposition> {
+        check_periods(observations.len(), self.period)?;
+        Ok(self.decompose_values(&to_f64s(observations)))
+    }
+
+    // Smooths every cycle-subseries and extends it by one cycle on either side, giving a
+    // series `2 * period` longer than the input.
+    fn cycle_subseries(&self, detrended: &[f64], robustness: Option<&[f64]>) -> Vec<f64> {
+        let n = detrended.len();
+        let period = self.period;
+        let mut result = vec![0.0; n + 2 * period];
+        for phase in 0..period {
+            let positions: Vec<usize> = (phase..n).step_by(period).collect();
+            let subseries: Vec<f64> = positions.iter().map(|&i| detrended[i]).collect();
+            let weights: Option<Vec<f64>> =
+                robustness.map(|r| positions.iter().map(|&i| r[i]).collect());
+            let len = subseries.len();
+            let smoothed: Vec<f64> = match self.seasonal_span {
+                Some(span) => {
+                    (0..(len + 2))
+                        .map(|position| {
+                            let at = position as f64 - 1.0;
+                            let nearest = position.saturating_sub(1).min(len - 1);
+                            loess(&subseries, weights.as_ref().map(|w| &w[..]), span, at)
+                                .unwrap_or(subseries[nearest])
+                        })
+                        .collect()
+                }
+                None => {
+                    let (total, weight): (f64, f64) = match weights {
+                        Some(ref w) => {
+                            (
+                                subseries.iter().zip(w.iter()).map(|(s, w)| s * w).sum(),
+                                w.iter().sum(),
+                            )
+                        }
+                        None => (subseries.iter().sum(), len as f64),
+                    };
+                    let mean = if weight > 0.0 {
+                        total / weight
+                    } else {
+                        median(subseries.clone())
+                    };
+                    vec![mean; len + 2]
+                }
+            };
+            for (cycle, value) in smoothed.into_iter().enumerate() {
+                let index = cycle * period + phase;
+                if index < result.len() {
+                    result[index] = value;
+                }
+            }
+        }
+        result
+    }
+
+    fn decompose_values(&self, values: &[f64]) -> Decomposition {
+        let n = values.len();
+        let period = self.period;
+        let low_pass_span = next_odd(period);
+        let trend_span = match self.seasonal_span {
+            Some(span) => {
+                let ratio = 1.5 * period as f64 / (1.0 - 1.5 / span as f64);
+                next_odd(ratio.ceil() as usize)
+            }
+            None => next_odd((1.5 * period as f64).ceil() as usize),
+        };
+        let (inner_iterations, outer_iterations) = if self.robust { (1, 15) } else { (2, 0) };
+        let mut trend = vec![0.0; n];
+        let mut seasonal = vec![0.0; n];
+        let mut robustness: Option<Vec<f64>> = None;
+        for outer in 0..(outer_iterations + 1) {
+            for _ in 0..inner_iterations {
+                let detrended: Vec<f64> =
+                    values.iter().zip(trend.iter()).map(|(v, t)| v - t).collect();
+                let cycles = self.cycle_subseries(&detrended, robustness.as_ref().map(|r| &r[..]));
+                let low_pass = loess_smooth(
+                    &moving_average(
+                        &moving_average(&moving_average(&cycles, period), period),
+                        3,
+                    ),
+                    None,
+                    low_pass_span,
+                );
+                seasonal = cycles[period..(period + n)]
+                    .iter()
+                    .zip(low_pass.iter())
+                    .map(|(c, l)| c - l)
+                    .collect();
+                let deseasonalized: Vec<f64> =
+                    values.iter().zip(seasonal.iter()).map(|(v, s)| v - s).collect();
+                trend = loess_smooth(
+                    &deseasonalized,
+                    robustness.as_ref().map(|r| &r[..]),
+                    trend_span,
+                );
+            }
+            if outer < outer_iterations {
+                let remainder: Vec<f64> = (0..n)
+                    .map(|i| (values[i] - seasonal[i] - trend[i]).abs())
+                    .collect();
+                let h = 6.0 * median(remainder.clone());
+                robustness = Some(
+                    remainder
+                        .iter()
+                        .map(|r| if h > 0.0 && r / h < 1.0 {
+                            let u = 1.0 - (r / h) * (r / h);
+                            u * u
+                        } else if h > 0.0 {
+                            0.0
+                        } else {
+                            1.0
+                        })
+                        .collect(),
+                );
+            }
+        }
+        let remainder = (0..n).map(|i| values[i] - seasonal[i] - trend[i]).collect();
+        Decomposition {
+            trend,
+            seasonal,
+            remainder,
+        }
+    }
+}
+
+fn check_periods(len: usize, period: usize) -> Result<()> {
+    if period < 2 || len < period * 2 {
+        Err(ErrorKind::NotEnoughPeriods(len, period).into())
+    } else {
+        Ok(())
+    }
+}
+
+/// A seasonally adjusted series, where `values[i]` corresponds to the original observation at
+/// index `i + offset`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SeasonallyAdjusted<F: Float> {
+    pub values: Vec<NonNaN<F>>,
+    pub offset: usize,
+}
+
+impl<F: Float> SeasonallyAdjusted<F> {
+    pub fn original_index(&self, index: usize) -> usize {
+        index + self.offset
+    }
+}
+
+/// How the seasonal pattern is removed before looking for changepoints.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Deseasonalizer {
+    /// Subtract the seasonal component of an STL decomposition, keeping trend and remainder.
+    Stl(Stl),
+    /// Subtract the median of each phase of the given period, centred on the overall median.
+    SeasonalMedian(usize),
+    /// Replace every observation by its difference with the one a period earlier. A level shift
+    /// becomes a pulse one period long, so this suits detectors of changes in spread or
+    /// distribution better than `EDMX`.
+    SeasonalDifference(usize),
+}
+
+impl Deseasonalizer {
+    pub fn adjust<F: Float>(&self, observations: &[NonNaN<F>]) -> Result<SeasonallyAdjusted<F>> {
+        let values = to_f64s(observations);
+        let (adjusted, offset) = match *self {
+            Deseasonalizer::Stl(ref stl) => {
+                check_periods(values.len(), stl.period)?;
+                let seasonal = stl.decompose_values(&values).seasonal;
+                (
+                    values
+                        .iter()
+                        .zip(seasonal.iter())
+                        .map(|(v, s)| v - s)
+                        .collect::<Vec<f64>>(),
+                    0,
+                )
+            }
+            Deseasonalizer::SeasonalMedian(period) => {
+                check_periods(values.len(), period)?;
+                let phase_medians: Vec<f64> = (0..period)
+                    .map(|phase| {
+                        median(values.iter().skip(phase).step_by(period).cloned().collect())
+                    })
+                    .collect();
+                let center = median(phase_medians.clone());
+                (
+                    values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| v - (phase_medians[i % period] - center))
+                        .collect(),
+                    0,
+                )
+            }
+            Deseasonalizer::SeasonalDifference(period) => {
+                check_periods(values.len(), period)?;
+                (
+                    values
+                        .iter()
+                        .skip(period)
+                        .zip(values.iter())
+                        .map(|(later, earlier)| later - earlier)
+                        .collect(),
+                    period,
+                )
+            }
+        };
+        Ok(SeasonallyAdjusted {
+            values: adjusted.into_iter().map(from_f64).collect(),
+            offset,
+        })
+    }
+}
+
+/// Runs any detector on the seasonally adjusted series, reporting locations as indices into the
+/// original series.
+///
+/// This is deliberately not a `ChangePointDetector`: shuffling the raw series, as the generic
+/// `permutation_test` would, breaks up its seasonality before every permutation is adjusted
+/// again, so the p-values would come out far too small. `Deseasonalized::permutation_test`
+/// adjusts once and permutes the adjusted series instead.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Deseasonalized<D> {
+    deseasonalizer: Deseasonalizer,
+    detector: D,
+}
+
+impl<D> Deseasonalized<D> {
+    pub fn new(deseasonalizer: Deseasonalizer, detector: D) -> Self {
+        Deseasonalized {
+            deseasonalizer,
+            detector,
+        }
+    }
+
+    /// Permutation tests the inner detector on the seasonally adjusted series, reporting the
+    /// changepoint as an index into the original series.
+    pub fn permutation_test<F, R>(
+        &self,
+        rng: R,
+        num_permutations: usize,
+        observations: &[NonNaN<F>],
+    ) -> Result<PermutationTestResult>
+    where
+        F: Float + Send + Sync,
+        D: ChangePointDetector<NonNaN<F>> + Send + Sync,
+        D::Statistic: Send + Sync,
+        R: Rng,
+    {
+        self.permutation_test_with_options(
+            rng,
+            num_permutations,
+            observations,
+            &PermutationTestOptions::default(),
+        )
+    }
+
+    pub fn permutation_test_with_options<F, R>(
+        &self,
+        rng: R,
+        num_permutations: usize,
+        observations: &[NonNaN<F>],
+        options: &PermutationTestOptions,
+    ) -> Result<PermutationTestResult>
+    where
+        F: Float + Send + Sync,
+        D: ChangePointDetector<NonNaN<F>> + Send + Sync,
+        D::Statistic: Send + Sync,
+        R: Rng,
+    {
+        let adjusted = self.deseasonalizer.adjust(observations)?;
+        let result = permutation_test_with_options(
+            &self.detector,
+            rng,
+            num_permutations,
+            &adjusted.values,
+            options,
+        )?;
+        Ok(PermutationTestResult {
+            changepoint_index: adjusted.original_index(result.changepoint_index),
+            ..result
+        })
+    }
+
+    /// The inner detector's best candidate on the adjusted series.
+    pub fn find_candidate<F>(
+        &self,
+        observations: &[NonNaN<F>],
+    ) -> Result<BestCandidate<D::Statistic>>
+    where
+        F: Float,
+        D: ChangePointDetector<NonNaN<F>>,
+    {
+        let adjusted = self.deseasonalizer.adjust(observations)?;
+        let BestCandidate {
+            statistic,
+            location,
+        } = self.detector.find_candidate(&adjusted.values)?;
+        Ok(BestCandidate {
+            statistic,
+            location: adjusted.original_index(location),
+        })
+    }
+
+    /// The inner detector's statistic curve over the adjusted series, if it has one.
+    pub fn statistic_curve<F>(
+        &self,
+        observations: &[NonNaN<F>],
+    ) -> Result<Option<Vec<BestCandidate<D::Statistic>>>>
+    where
+        F: Float,
+        D: ChangePointDetector<NonNaN<F>>,
+    {
+        let adjusted = self.deseasonalizer.adjust(observations)?;
+        Ok(self.detector.statistic_curve(&adjusted.values)?.map(|curve| {
+            curve
+                .into_iter()
+                .map(|BestCandidate { statistic, location }| {
+                    BestCandidate {
+                        statistic,
+                        location: adjusted.original_index(location),
+                    }
+                })
+                .collect()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::edm_x::edm_x::EDMX;
+    use rand::SeedableRng;
+    use rand::distributions::{IndependentSample, Normal};
+    use mersenne_twister::MersenneTwister;
+    use std::f64::consts::PI;
+
+    const PERIOD: usize = 24;
+
+    // A strong daily cycle with a level shift of `shift` at index 300.
+    fn daily_cycle(shift: f64) -> Vec<NonNaN<f64>> {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let noise = Normal::new(0.0, 1.0);
+        (0..600)
+            .map(|t| {
+                let cycle = 20.0 * (2.0 * PI * t as f64 / PERIOD as f64).sin();
+                let level = if t < 300 { 50.0 } else { 50.0 + shift };
+                NonNaN::new(level + cycle + noise.ind_sample(&mut rng)).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stl_recovers_seasonal_component() {
+        let input = daily_cycle(0.0);
+        for stl in &[Stl::new(PERIOD), Stl::new(PERIOD).with_seasonal_span(7)] {
+            let decomposition = stl.decompose(&input).unwrap();
+            let mean_error = decomposition
+                .seasonal
+                .iter()
+                .enumerate()
+                .map(|(t, seasonal)| {
+                    let expected = 20.0 * (2.0 * PI * t as f64 / PERIOD as f64).sin();
+                    (seasonal - expected).abs()
+                })
+                .sum::<f64>() / 600.0;
+            assert!(mean_error < 0.5);
+            let mean_trend = decomposition.trend.iter().sum::<f64>() / 600.0;
+            assert!((mean_trend - 50.0).abs() < 0.5);
+        }
+    }
+
+    #[test]
+    fn deseasonalized_edm_x_finds_level_shift() {
+        let input = daily_cycle(8.0);
+        let tolerance = 15;
+        for deseasonalizer in &[
+            Deseasonalizer::Stl(Stl::new(PERIOD)),
+            Deseasonalizer::SeasonalMedian(PERIOD),
+        ]
+        {
+            let detector = Deseasonalized::new(deseasonalizer.clone(), EDMX::new(30));
+            let best_candidate = detector.find_candidate(&input).unwrap();
+            assert!((best_candidate.location as i64 - 300).abs() < tolerance);
+            let curve = detector.statistic_curve(&input).unwrap().unwrap();
+            assert_eq!(curve.into_iter().max(), Some(best_candidate));
+        }
+    }
+
+    #[test]
+    fn seasonal_difference_maps_indices_back() {
+        let input = daily_cycle(0.0);
+        let adjusted = Deseasonalizer::SeasonalDifference(PERIOD)
+            .adjust(&input)
+            .unwrap();
+        assert_eq!(adjusted.values.len(), input.len() - PERIOD);
+        assert_eq!(adjusted.original_index(0), PERIOD);
+        assert!(Deseasonalizer::SeasonalMedian(PERIOD)
+            .adjust(&input[..30])
+            .is_err());
+    }
+
+    #[test]
+    fn permutation_test_permutes_the_adjusted_series() {
+        let tolerance = 15;
+        for deseasonalizer in &[
+            Deseasonalizer::SeasonalMedian(PERIOD),
+            Deseasonalizer::SeasonalDifference(PERIOD),
+        ]
+        {
+            let detector = Deseasonalized::new(deseasonalizer.clone(), EDMX::new(30));
+            let rng: MersenneTwister = SeedableRng::from_seed(0x5678);
+            let flat = detector.permutation_test(rng, 19, &daily_cycle(0.0)).unwrap();
+            assert!(flat.p_value > 0.05, "{:?} gave {}", deseasonalizer, flat.p_value);
+        }
+        let detector = Deseasonalized::new(Deseasonalizer::SeasonalMedian(PERIOD), EDMX::new(30));
+        let rng: MersenneTwister = SeedableRng::from_seed(0x5678);
+        let shifted = detector.permutation_test(rng, 19, &daily_cycle(8.0)).unwrap();
+        assert!(shifted.p_value <= 0.05);
+        assert!((shifted.changepoint_index as i64 - 300).abs() < tolerance);
+    }
+}
@@ -0,0 +1,207 @@
+use num::Float;
+use algo::changepoint::ChangePointDetector;
+use algo::non_nan::NonNaN;
+use algo::permutation_test::{PermutationTestOptions, PermutationTestResult,
+                             permutation_test_with_options, test_against_resamples};
+use algo::stats::{from_f64, to_f64s};
+use mersenne_twister::MersenneTwister;
+use rand::SeedableRng;
+use rand::distributions::{IndependentSample, Normal};
+
+use errors::*;
+
+/// A procedure deciding how likely the changepoint a detector finds in the observations is to
+/// have arisen by chance.
+pub trait SignificanceTest<T, B: ChangePointDetector<T>> {
+    fn test(&self, detector: &B, observations: &[T]) -> Result<PermutationTestResult>;
+}
+
+/// Compares the statistic against those of random shuffles of the observations, which is valid
+/// for any detector as long as the observations are exchangeable without a change.
+#[derive(Clone, Debug)]
+pub struct PermutationTest {
+    num_permutations: usize,
+    seed: u64,
+    options: PermutationTestOptions,
+}
+
+impl PermutationTest {
+    pub fn new(num_permutations: usize, seed: u64) -> Self {
+        PermutationTest {
+            num_permutations,
+            seed,
+            options: PermutationTestOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: PermutationTestOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<T, B> SignificanceTest<T, B> for PermutationTest
+where
+    T: Clone + Send + Sync,
+    B: ChangePointDetector<T> + Send + Sync,
+    B::Statistic: Send + Sync,
+{
+    fn test(&self, detector: &B, observations: &[T]) -> Result<PermutationTestResult> {
+        let rng: MersenneTwister = SeedableRng::from_seed(self.seed);
+        permutation_test_with_options(
+            detector,
+            rng,
+            self.num_permutations,
+            observations,
+            &self.options,
+        )
+    }
+}
+
+/// Compares the statistic against those of series drawn from a normal distribution with the
+/// mean and standard deviation of the observations, which unlike shuffling keeps no trace of a
+/// change in the resampled series.
+///
+/// Cancelling it fails with `PermutationTestCancelled`, counting the samples as permutations.
+#[derive(Clone, Debug)]
+pub struct ParametricBootstrap {
+    num_samples: usize,
+    seed: u64,
+    options: PermutationTestOptions,
+}
+
+impl ParametricBootstrap {
+    pub fn new(num_samples: usize, seed: u64) -> Self {
+        ParametricBootstrap {
+            num_samples,
+            seed,
+            options: PermutationTestOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: PermutationTestOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<F, B> SignificanceTest<NonNaN<F>, B> for ParametricBootstrap
+where
+    F: Float + Send + Sync,
+    B: ChangePointDetector<NonNaN<F>> + Send + Sync,
+    B::Statistic: Send + Sync,
+{
+    fn test(&self, detector: &B, observations: &[NonNaN<F>]) -> Result<PermutationTestResult> {
+        let candidate = detector.find_candidate(observations)?;
+        let values = to_f64s(observations);
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|value| (value - mean) * (value - mean)).sum::<f64>() /
+            (n - 1.0).max(1.0);
+        let null_distribution = Normal::new(mean, variance.sqrt());
+        let mut rng: MersenneTwister = SeedableRng::from_seed(self.seed);
+        let samples = (0..self.num_samples)
+            .map(|_| {
+                values
+                    .iter()
+                    .map(|_| from_f64(null_distribution.ind_sample(&mut rng)))
+                    .collect()
+            })
+            .collect();
+        test_against_resamples(detector, candidate, samples, &self.options)
+    }
+}
+
+/// Reads the p-value off the asymptotic null distribution of the statistic, which needs no
+/// resampling but only exists for some detectors, see `ChangePointDetector::asymptotic_p_value`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Asymptotic;
+
+impl<T, B: ChangePointDetector<T>> SignificanceTest<T, B> for Asymptotic {
+    fn test(&self, detector: &B, observations: &[T]) -> Result<PermutationTestResult> {
+        let candidate = detector.find_candidate(observations)?;
+        Ok(PermutationTestResult {
+            p_value: detector.asymptotic_p_value(observations, &candidate)?,
+            changepoint_index: candidate.location,
+        })
+    }
+}
+
+/// A significance test picked by configuration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SignificanceMethod {
+    Permutation { permutations: usize, seed: u64 },
+    ParametricBootstrap { samples: usize, seed: u64 },
+    Asymptotic,
+}
+
+impl<F, B> SignificanceTest<NonNaN<F>, B> for SignificanceMethod
+where
+    F: Float + Send + Sync,
+    B: ChangePointDetector<NonNaN<F>> + Send + Sync,
+    B::Statistic: Send + Sync,
+{
+    fn test(&self, detector: &B, observations: &[NonNaN<F>]) -> Result<PermutationTestResult> {
+        match *self {
+            SignificanceMethod::Permutation { permutations, seed } => {
+                PermutationTest::new(permutations, seed).test(detector, observations)
+            }
+            SignificanceMethod::ParametricBootstrap { samples, seed } => {
+                ParametricBootstrap::new(samples, seed).test(detector, observations)
+            }
+            SignificanceMethod::Asymptotic => Asymptotic.test(detector, observations),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::edm_x::edm_x::EDMX;
+    use algo::kcp::{KCP, Kernel};
+    use generator::normal_series;
+
+    #[test]
+    fn every_method_tells_shifted_from_flat_series() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let shifted = normal_series(&mut rng, &[(100, 10.0, 5.0), (100, 16.0, 5.0)]);
+        let flat = normal_series(&mut rng, &[(200, 10.0, 5.0)]);
+        let detector = KCP::new(Kernel::Linear, 20, 1);
+        let methods = [
+            SignificanceMethod::Permutation {
+                permutations: 19,
+                seed: 1,
+            },
+            SignificanceMethod::ParametricBootstrap {
+                samples: 19,
+                seed: 1,
+            },
+            SignificanceMethod::Asymptotic,
+        ];
+        for method in &methods {
+            let result = method.test(&detector, &shifted).unwrap();
+            assert!(result.p_value <= 0.05, "{:?} gave {}", method, result.p_value);
+            assert!((result.changepoint_index as i64 - 100).abs() <= 10);
+            let result = method.test(&detector, &flat).unwrap();
+            assert!(result.p_value > 0.05, "{:?} gave {}", method, result.p_value);
+        }
+    }
+
+    #[test]
+    fn asymptotic_test_needs_a_known_distribution() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let observations = normal_series(&mut rng, &[(100, 10.0, 5.0)]);
+        for result in &[
+            Asymptotic.test(&EDMX::new(20), &observations),
+            Asymptotic.test(&KCP::new(Kernel::Gaussian(None), 20, 1), &observations),
+        ]
+        {
+            match *result.as_ref().unwrap_err().kind() {
+                ErrorKind::NoAsymptoticDistribution => {}
+                ref other => panic!("Unexpected error {:?}", other),
+            }
+        }
+    }
+}
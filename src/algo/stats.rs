@@ -0,0 +1,188 @@
+use num::Float;
+use algo::non_nan::NonNaN;
+
+pub fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(|a, b| a.partial_cmp(b).expect("Finite values are never NaN"));
+    let len = values.len();
+    if len % 2 == 1 {
+        values[len / 2]
+    } else {
+        (values[len / 2 - 1] + values[len / 2]) / 2.0
+    }
+}
+
+pub fn to_f64<F: Float>(value: &NonNaN<F>) -> f64 {
+    value.value().to_f64().expect(
+        "Finite floating point values always convert to f64",
+    )
+}
+
+pub fn to_f64s<F: Float>(observations: &[NonNaN<F>]) -> Vec<f64> {
+    observations.iter().map(to_f64).collect()
+}
+
+// Converts a computed statistic back to a NonNaN, clipping overflow to the largest finite value.
+pub fn from_f64<F: Float>(value: f64) -> NonNaN<F> {
+    F::from(value).and_then(NonNaN::new).unwrap_or_else(|| {
+        NonNaN::new(if value < 0.0 {
+            F::min_value()
+        } else {
+            F::max_value()
+        }).expect("The largest and smallest floats are finite")
+    })
+}
+
+pub fn median_absolute_deviation(values: &[f64]) -> f64 {
+    let center = median(values.to_vec());
+    median(values.iter().map(|value| (value - center).abs()).collect())
+}
+
+// Lanczos approximation (g = 7, n = 9) of the logarithm of the gamma function.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        let pi = ::std::f64::consts::PI;
+        (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x)
+    } else {
+        let x = x - 1.0;
+        let t = x + 7.5;
+        let series = COEFFICIENTS
+            .iter()
+            .enumerate()
+            .skip(1)
+            .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64));
+        0.5 * (2.0 * ::std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+    }
+}
+
+// Continued fraction for the regularized incomplete beta function, by the modified Lentz
+// method.
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut result = d;
+    for m in 1..300 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 + even * d;
+        d = if d.abs() < TINY { 1.0 / TINY } else { 1.0 / d };
+        c = 1.0 + even / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        result *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 + odd * d;
+        d = if d.abs() < TINY { 1.0 / TINY } else { 1.0 / d };
+        c = 1.0 + odd / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        let step = d * c;
+        result *= step;
+        if (step - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    result
+}
+
+fn regularized_incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln())
+        .exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+pub fn student_t_cdf(t: f64, degrees_of_freedom: f64) -> f64 {
+    let tail = 0.5 *
+        regularized_incomplete_beta(
+            degrees_of_freedom / 2.0,
+            0.5,
+            degrees_of_freedom / (degrees_of_freedom + t * t),
+        );
+    if t >= 0.0 { 1.0 - tail } else { tail }
+}
+
+// Inverts the CDF by bisection, which is plenty fast for the handful of quantiles needed.
+pub fn student_t_quantile(probability: f64, degrees_of_freedom: f64) -> f64 {
+    if probability < 0.5 {
+        return -student_t_quantile(1.0 - probability, degrees_of_freedom);
+    }
+    let mut low = 0.0;
+    let mut high = 1.0;
+    while student_t_cdf(high, degrees_of_freedom) < probability && high < 1e12 {
+        low = high;
+        high *= 2.0;
+    }
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+        if student_t_cdf(middle, degrees_of_freedom) < probability {
+            low = middle;
+        } else {
+            high = middle;
+        }
+        if high - low < 1e-12 * high.max(1.0) {
+            break;
+        }
+    }
+    (low + high) / 2.0
+}
+
+// The probability that |B(t)| / sqrt(t (1 - t)) exceeds `b` somewhere in [t0, 1 - t0], for a
+// Brownian bridge B, by the tail approximation of James, James and Siegmund (1987). Below b = 1
+// the approximation breaks down, and the probability is close to one anyway.
+pub fn standardized_bridge_exceedance(b: f64, t0: f64) -> f64 {
+    if b < 1.0 {
+        return 1.0;
+    }
+    let span = 2.0 * ((1.0 - t0) / t0).ln();
+    let density = (-b * b / 2.0).exp() / (2.0 * ::std::f64::consts::PI).sqrt();
+    (b * density * ((1.0 - 1.0 / (b * b)) * span + 4.0 / (b * b))).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn student_t_quantiles_match_tables() {
+        assert!((student_t_quantile(0.975, 1.0) - 12.706).abs() < 1e-3);
+        assert!((student_t_quantile(0.975, 10.0) - 2.228).abs() < 1e-3);
+        assert!((student_t_quantile(0.995, 30.0) - 2.750).abs() < 1e-3);
+        assert!((student_t_quantile(0.05, 5.0) + 2.015).abs() < 1e-3);
+        assert!((student_t_cdf(0.0, 7.0) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bridge_exceedance_matches_critical_values() {
+        // Andrews (1993) gives 8.85 as the 5% critical value of the squared statistic for
+        // t0 = 0.15.
+        assert!((standardized_bridge_exceedance(8.85f64.sqrt(), 0.15) - 0.05).abs() < 0.005);
+        assert_eq!(standardized_bridge_exceedance(0.5, 0.15), 1.0);
+    }
+}
@@ -0,0 +1,273 @@
+use rand::Rng;
+use algo::best_candidate::BestCandidate;
+use algo::changepoint::ChangePointDetector;
+use algo::permutation_test::{permutation_test, PermutationTestResult};
+
+use errors::*;
+
+/// What to do with observations sharing a timestamp with the observation before them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DuplicatePolicy {
+    Reject,
+    KeepFirst,
+    KeepLast,
+}
+
+/// Values paired with non-decreasing timestamps, which need not be evenly spaced. Timestamps are
+/// plain integers, so any unit (seconds, milliseconds since the epoch, ...) works as long as it
+/// is used consistently.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeSeries<T> {
+    timestamps: Vec<i64>,
+    values: Vec<T>,
+}
+
+/// The observations `start_index..end_index`, spanning timestamps `start` to `end` inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimeSpan {
+    pub start_index: usize,
+    pub end_index: usize,
+    pub start: i64,
+    pub end: i64,
+}
+
+impl<T> TimeSeries<T> {
+    /// Builds a series, rejecting duplicate timestamps.
+    pub fn new(timestamps: Vec<i64>, values: Vec<T>) -> Result<Self> {
+        TimeSeries::with_duplicates(timestamps, values, DuplicatePolicy::Reject)
+    }
+
+    pub fn with_duplicates(
+        timestamps: Vec<i64>,
+        values: Vec<T>,
+        policy: DuplicatePolicy,
+    ) -> Result<Self> {
+        if timestamps.len() != values.len() {
+            return Err(
+                ErrorKind::LengthMismatch(timestamps.len(), values.len()).into(),
+            );
+        }
+        let mut series = TimeSeries {
+            timestamps: Vec::with_capacity(timestamps.len()),
+            values: Vec::with_capacity(values.len()),
+        };
+        for (index, (timestamp, value)) in timestamps.into_iter().zip(values).enumerate() {
+            match series.timestamps.last().cloned() {
+                Some(previous) if timestamp < previous => {
+                    return Err(
+                        ErrorKind::UnorderedTimestamps(index, previous, timestamp).into(),
+                    );
+                }
+                Some(previous) if timestamp == previous => {
+                    match policy {
+                        DuplicatePolicy::Reject => {
+                            return Err(ErrorKind::DuplicateTimestamp(index, timestamp).into());
+                        }
+                        DuplicatePolicy::KeepFirst => {}
+                        DuplicatePolicy::KeepLast => {
+                            *series.values.last_mut().expect(
+                                "A previous timestamp implies a previous value",
+                            ) = value;
+                        }
+                    }
+                }
+                _ => {
+                    series.timestamps.push(timestamp);
+                    series.values.push(value);
+                }
+            }
+        }
+        Ok(series)
+    }
+
+    pub fn timestamps(&self) -> &[i64] {
+        &self.timestamps
+    }
+
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn into_parts(self) -> (Vec<i64>, Vec<T>) {
+        (self.timestamps, self.values)
+    }
+
+    /// The span of the observations `start_index..end_index`, or `None` when the range is empty.
+    pub fn span(&self, start_index: usize, end_index: usize) -> Option<TimeSpan> {
+        if start_index >= end_index {
+            return None;
+        }
+        Some(TimeSpan {
+            start_index,
+            end_index,
+            start: self.timestamps[start_index],
+            end: self.timestamps[end_index - 1],
+        })
+    }
+
+    // The segments on either side of a change whose first new observation is at `index`, of which
+    // only the one before can be empty.
+    fn split_spans(&self, index: usize) -> (Option<TimeSpan>, TimeSpan) {
+        (
+            self.span(0, index),
+            self.span(index, self.len()).expect(
+                "Detectors report the index of an observation, so the change has one after it",
+            ),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimedCandidate<S> {
+    pub statistic: S,
+    pub changepoint_index: usize,
+    pub changepoint_timestamp: i64,
+    /// `None` when the change is at the very start of the series.
+    pub before: Option<TimeSpan>,
+    pub after: TimeSpan,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimedPermutationTestResult {
+    pub p_value: f64,
+    pub changepoint_index: usize,
+    pub changepoint_timestamp: i64,
+    /// `None` when the change is at the very start of the series.
+    pub before: Option<TimeSpan>,
+    pub after: TimeSpan,
+}
+
+/// Runs the detector on the values of the series, reporting when the change happened.
+pub fn find_candidate_in_series<T, B>(
+    algorithm: &B,
+    series: &TimeSeries<T>,
+) -> Result<TimedCandidate<B::Statistic>>
+where
+    B: ChangePointDetector<T>,
+{
+    let BestCandidate {
+        statistic,
+        location,
+    } = algorithm.find_candidate(series.values())?;
+    let (before, after) = series.split_spans(location);
+    Ok(TimedCandidate {
+        statistic,
+        changepoint_index: location,
+        changepoint_timestamp: series.timestamps[location],
+        before,
+        after,
+    })
+}
+
+/// `permutation_test` on the values of the series, reporting when the change happened.
+pub fn permutation_test_series<T, B, R>(
+    algorithm: &B,
+    rng: R,
+    num_permutations: usize,
+    series: &TimeSeries<T>,
+) -> Result<TimedPermutationTestResult>
+where
+    T: Clone + Send + Sync,
+    B: ChangePointDetector<T> + Send + Sync,
+    B::Statistic: Send + Sync,
+    R: Rng,
+{
+    let PermutationTestResult {
+        p_value,
+        changepoint_index,
+    } = permutation_test(algorithm, rng, num_permutations, series.values())?;
+    let (before, after) = series.split_spans(changepoint_index);
+    Ok(TimedPermutationTestResult {
+        p_value,
+        changepoint_index,
+        changepoint_timestamp: series.timestamps[changepoint_index],
+        before,
+        after,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::edm_x::edm_x::EDMX;
+    use algo::non_nan::NonNaN;
+    use mersenne_twister::MersenneTwister;
+    use rand::SeedableRng;
+    use rand::distributions::{IndependentSample, Normal};
+
+    #[test]
+    fn duplicate_and_unordered_timestamps() {
+        let timestamps = vec![1, 2, 2, 5];
+        let values = vec!['a', 'b', 'c', 'd'];
+        assert!(TimeSeries::new(timestamps.clone(), values.clone()).is_err());
+        let first = TimeSeries::with_duplicates(
+            timestamps.clone(),
+            values.clone(),
+            DuplicatePolicy::KeepFirst,
+        ).unwrap();
+        assert_eq!(first.timestamps(), &[1, 2, 5]);
+        assert_eq!(first.values(), &['a', 'b', 'd']);
+        let last = TimeSeries::with_duplicates(timestamps, values, DuplicatePolicy::KeepLast)
+            .unwrap();
+        assert_eq!(last.values(), &['a', 'c', 'd']);
+        match *TimeSeries::new(vec![1, 3, 2], vec![0, 0, 0]).unwrap_err().kind() {
+            ErrorKind::UnorderedTimestamps(2, 3, 2) => {}
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+        assert!(TimeSeries::new(vec![1, 2], vec![0]).is_err());
+    }
+
+    #[test]
+    fn a_change_at_the_start_has_nothing_before_it() {
+        let series = TimeSeries::new(vec![10, 20, 30], vec!['a', 'b', 'c']).unwrap();
+        let (before, after) = series.split_spans(0);
+        assert_eq!(before, None);
+        assert_eq!(after, series.span(0, 3).unwrap());
+        assert_eq!(after.end, 30);
+        assert_eq!(series.span(2, 2), None);
+    }
+
+    #[test]
+    fn permutation_test_reports_change_timestamp() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let before_change_dist = Normal::new(10.0, 5.0);
+        let after_change_dist = Normal::new(30.0, 5.0);
+        // Irregular sampling: gaps alternate between 7 and 13 seconds.
+        let timestamps: Vec<i64> = (0..300).map(|i| 1_500_000_000 + 10 * i + 3 * (i % 2)).collect();
+        let values: Vec<NonNaN<f64>> = (0..300)
+            .map(|i| {
+                let dist = if i < 200 {
+                    before_change_dist
+                } else {
+                    after_change_dist
+                };
+                NonNaN::new(dist.ind_sample(&mut rng)).unwrap()
+            })
+            .collect();
+        let series = TimeSeries::new(timestamps.clone(), values).unwrap();
+        let result = permutation_test_series(&EDMX::new(30), rng, 10, &series).unwrap();
+        assert!(result.p_value <= 0.1);
+        assert!((result.changepoint_index as i64 - 200).abs() < 10);
+        assert_eq!(
+            result.changepoint_timestamp,
+            timestamps[result.changepoint_index]
+        );
+        let before = result.before.unwrap();
+        assert_eq!(before.start, timestamps[0]);
+        assert_eq!(before.end_index, result.after.start_index);
+        assert_eq!(result.after.end, timestamps[299]);
+    }
+}
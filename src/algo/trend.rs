@@ -0,0 +1,598 @@
+use num::Float;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use algo::best_candidate::BestCandidate;
+use algo::changepoint::{ChangePointDetector, check_observations};
+use algo::non_nan::NonNaN;
+use algo::stats::{from_f64, median, to_f64s};
+
+use errors::*;
+
+/// Whether neighbouring segments of a piecewise-linear fit must meet at the changepoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Continuity {
+    /// Only the slope changes; the fitted line bends but never jumps.
+    Continuous,
+    /// Both the level and the slope may change.
+    Discontinuous,
+}
+
+/// A linear piece of the fit, covering observations `start..end` with fitted values
+/// `intercept + slope * index`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrendSegment {
+    pub start: usize,
+    pub end: usize,
+    pub intercept: f64,
+    pub slope: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SlopeChange {
+    /// Index of the first observation following the new slope.
+    pub location: usize,
+    pub slope_before: f64,
+    pub slope_after: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrendFit {
+    pub segments: Vec<TrendSegment>,
+    pub changes: Vec<SlopeChange>,
+    pub residual_sum_of_squares: f64,
+}
+
+// Prefix sums giving the least-squares line of any range of observations in constant time.
+struct LineSums {
+    t: Vec<f64>,
+    tt: Vec<f64>,
+    y: Vec<f64>,
+    ty: Vec<f64>,
+    yy: Vec<f64>,
+}
+
+struct Line {
+    intercept: f64,
+    slope: f64,
+    residual_sum_of_squares: f64,
+}
+
+impl LineSums {
+    fn new(values: &[f64]) -> Self {
+        let mut sums = LineSums {
+            t: vec![0.0],
+            tt: vec![0.0],
+            y: vec![0.0],
+            ty: vec![0.0],
+            yy: vec![0.0],
+        };
+        for (index, value) in values.iter().enumerate() {
+            let t = index as f64;
+            let next_t = sums.t[index] + t;
+            let next_tt = sums.tt[index] + t * t;
+            let next_y = sums.y[index] + value;
+            let next_ty = sums.ty[index] + t * value;
+            let next_yy = sums.yy[index] + value * value;
+            sums.t.push(next_t);
+            sums.tt.push(next_tt);
+            sums.y.push(next_y);
+            sums.ty.push(next_ty);
+            sums.yy.push(next_yy);
+        }
+        sums
+    }
+
+    fn line(&self, start: usize, end: usize) -> Line {
+        let n = (end - start) as f64;
+        let t = self.t[end] - self.t[start];
+        let y = self.y[end] - self.y[start];
+        let centered_tt = self.tt[end] - self.tt[start] - t * t / n;
+        let centered_ty = self.ty[end] - self.ty[start] - t * y / n;
+        let centered_yy = self.yy[end] - self.yy[start] - y * y / n;
+        let slope = if centered_tt > 0.0 {
+            centered_ty / centered_tt
+        } else {
+            0.0
+        };
+        Line {
+            intercept: (y - slope * t) / n,
+            slope,
+            residual_sum_of_squares: (centered_yy - slope * centered_ty).max(0.0),
+        }
+    }
+}
+
+// A robust noise variance estimate: first differences remove the trend and turn the noise
+// variance into twice its value, and their MAD is unaffected by the few changepoints.
+//
+// On quantized data most differences can be equal, which makes their MAD zero, so the plain
+// variance of the differences is used instead. A floor relative to the magnitude of the values
+// keeps the penalty positive even for exact lines.
+fn noise_variance(values: &[f64]) -> f64 {
+    let differences: Vec<f64> = values.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let center = median(differences.clone());
+    let mad = median(differences.iter().map(|d| (d - center).abs()).collect());
+    if mad > 0.0 {
+        let sigma = 1.4826 * mad / 2.0.sqrt();
+        return sigma * sigma;
+    }
+    let count = differences.len() as f64;
+    let mean = differences.iter().sum::<f64>() / count;
+    let variance = differences.iter().map(|d| (d - mean) * (d - mean)).sum::<f64>() / count /
+        2.0;
+    let magnitude = values.iter().fold(1.0, |largest: f64, value| largest.max(value.abs()));
+    variance.max(f64::EPSILON * magnitude * magnitude)
+}
+
+fn dot(left: &[f64], right: &[f64]) -> f64 {
+    left.iter().zip(right.iter()).map(|(l, r)| l * r).sum()
+}
+
+// An orthonormal basis for the span of the regressors chosen so far.
+struct Basis {
+    columns: Vec<Vec<f64>>,
+}
+
+impl Basis {
+    fn residualize(&self, column: &[f64]) -> Vec<f64> {
+        let mut result = column.to_vec();
+        for basis_column in &self.columns {
+            let projection = dot(&result, basis_column);
+            for (value, basis_value) in result.iter_mut().zip(basis_column.iter()) {
+                *value -= projection * basis_value;
+            }
+        }
+        result
+    }
+
+    fn push(&mut self, column: &[f64]) {
+        let residual = self.residualize(column);
+        let norm = dot(&residual, &residual).sqrt();
+        if norm > 0.0 {
+            self.columns.push(residual.iter().map(|value| value / norm).collect());
+        }
+    }
+
+    fn fitted(&self, values: &[f64]) -> Vec<f64> {
+        let mut result = vec![0.0; values.len()];
+        for basis_column in &self.columns {
+            let projection = dot(values, basis_column);
+            for (value, basis_value) in result.iter_mut().zip(basis_column.iter()) {
+                *value += projection * basis_value;
+            }
+        }
+        result
+    }
+}
+
+fn hinge(n: usize, knot: usize) -> Vec<f64> {
+    (0..n)
+        .map(|t| if t > knot { (t - knot) as f64 } else { 0.0 })
+        .collect()
+}
+
+fn is_valid_knot(knot: usize, knots: &[usize], n: usize, min_len: usize) -> bool {
+    knot >= min_len && n - knot >= min_len &&
+        knots.iter().all(|&existing| existing.abs_diff(knot) >= min_len)
+}
+
+// The knot whose hinge lowers the residual sum of squares of the current continuous fit the
+// most, along with that reduction.
+fn best_knot(
+    basis: &Basis,
+    residuals: &[f64],
+    knots: &[usize],
+    min_len: usize,
+) -> Option<(f64, usize)> {
+    let n = residuals.len();
+    (0..n)
+        .filter(|&knot| is_valid_knot(knot, knots, n, min_len))
+        .filter_map(|knot| {
+            let column = basis.residualize(&hinge(n, knot));
+            let norm = dot(&column, &column);
+            if norm > 0.0 {
+                let projection = dot(residuals, &column);
+                Some((projection * projection / norm, knot))
+            } else {
+                None
+            }
+        })
+        .fold(None, |best, candidate| match best {
+            Some(best) if best.0 >= candidate.0 => Some(best),
+            _ => Some(candidate),
+        })
+}
+
+fn linear_basis(n: usize) -> Basis {
+    let mut basis = Basis { columns: Vec::new() };
+    basis.push(&vec![1.0; n]);
+    basis.push(&(0..n).map(|t| t as f64).collect::<Vec<f64>>());
+    basis
+}
+
+fn residuals_of(basis: &Basis, values: &[f64]) -> Vec<f64> {
+    basis
+        .fitted(values)
+        .iter()
+        .zip(values.iter())
+        .map(|(fit, value)| value - fit)
+        .collect()
+}
+
+fn changes_between(segments: &[TrendSegment]) -> Vec<SlopeChange> {
+    segments
+        .windows(2)
+        .map(|pair| {
+            SlopeChange {
+                location: pair[1].start,
+                slope_before: pair[0].slope,
+                slope_after: pair[1].slope,
+            }
+        })
+        .collect()
+}
+
+/// Detects changes in the slope of a linear trend, such as a memory leak starting or a disk
+/// filling up faster, by fitting piecewise-linear segments of at least `min_segment_length`
+/// observations.
+///
+/// A changepoint is kept when it lowers the residual sum of squares by more than the penalty,
+/// which defaults to a BIC-style penalty using a robust estimate of the noise variance.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedTrendDetector"))]
+pub struct TrendDetector {
+    min_segment_length: usize,
+    continuity: Continuity,
+    penalty: Option<f64>,
+}
+
+// Deserialized configurations are checked like those built by `TrendDetector::new`.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedTrendDetector {
+    min_segment_length: usize,
+    continuity: Continuity,
+    penalty: Option<f64>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedTrendDetector> for TrendDetector {
+    type Error = Error;
+
+    fn try_from(unchecked: UncheckedTrendDetector) -> Result<Self> {
+        let detector = TrendDetector {
+            min_segment_length: unchecked.min_segment_length,
+            continuity: unchecked.continuity,
+            penalty: unchecked.penalty,
+        };
+        detector.check_parameters()?;
+        Ok(detector)
+    }
+}
+
+impl TrendDetector {
+    pub fn new(min_segment_length: usize, continuity: Continuity) -> Self {
+        TrendDetector {
+            min_segment_length,
+            continuity,
+            penalty: None,
+        }
+    }
+
+    pub fn with_penalty(mut self, penalty: f64) -> Self {
+        self.penalty = Some(penalty);
+        self
+    }
+
+    // A line through fewer than two observations is not determined.
+    pub(crate) fn check_parameters(&self) -> Result<()> {
+        if self.min_segment_length < 2 {
+            return Err(
+                ErrorKind::InvalidParameter(
+                    "min_segment_length".to_string(),
+                    "must be at least 2".to_string(),
+                ).into(),
+            );
+        }
+        match self.penalty {
+            Some(penalty) if !(penalty >= 0.0 && penalty.is_finite()) => Err(
+                ErrorKind::InvalidParameter(
+                    "penalty".to_string(),
+                    format!("{} is not a finite non-negative number", penalty),
+                ).into(),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_length(&self, n: usize) -> Result<()> {
+        self.check_parameters()?;
+        check_observations(n, self.min_segment_length * 2)
+    }
+
+    // Every changepoint costs the parameters it introduces: a location and a slope, plus a
+    // level when segments need not meet.
+    fn penalty(&self, values: &[f64]) -> f64 {
+        self.penalty.unwrap_or_else(|| {
+            let parameters = match self.continuity {
+                Continuity::Continuous => 2.0,
+                Continuity::Discontinuous => 3.0,
+            };
+            parameters * noise_variance(values) * (values.len() as f64).ln()
+        })
+    }
+
+    pub fn fit<F: Float>(&self, observations: &[NonNaN<F>]) -> Result<TrendFit> {
+        let values = to_f64s(observations);
+        self.check_length(values.len())?;
+        Ok(match self.continuity {
+            Continuity::Continuous => self.fit_continuous(&values),
+            Continuity::Discontinuous => self.fit_discontinuous(&values),
+        })
+    }
+
+    // Optimal partitioning: the exact minimum of the penalised cost over all segmentations.
+    fn fit_discontinuous(&self, values: &[f64]) -> TrendFit {
+        let n = values.len();
+        let min_len = self.min_segment_length;
+        let penalty = self.penalty(values);
+        let sums = LineSums::new(values);
+        let mut best_cost = vec![f64::INFINITY; n + 1];
+        let mut last_start = vec![0; n + 1];
+        best_cost[0] = -penalty;
+        for end in min_len..(n + 1) {
+            for start in (0..(end - min_len + 1)).filter(|&start| start == 0 || start >= min_len) {
+                let candidate = best_cost[start] + sums.line(start, end).residual_sum_of_squares +
+                    penalty;
+                if candidate < best_cost[end] {
+                    best_cost[end] = candidate;
+                    last_start[end] = start;
+                }
+            }
+        }
+        let mut boundaries = vec![n];
+        let mut end = n;
+        while end > 0 {
+            end = last_start[end];
+            boundaries.push(end);
+        }
+        boundaries.reverse();
+        let segments: Vec<TrendSegment> = boundaries
+            .windows(2)
+            .map(|pair| {
+                let line = sums.line(pair[0], pair[1]);
+                TrendSegment {
+                    start: pair[0],
+                    end: pair[1],
+                    intercept: line.intercept,
+                    slope: line.slope,
+                }
+            })
+            .collect();
+        let residual_sum_of_squares = boundaries
+            .windows(2)
+            .map(|pair| sums.line(pair[0], pair[1]).residual_sum_of_squares)
+            .sum();
+        TrendFit {
+            changes: changes_between(&segments),
+            segments,
+            residual_sum_of_squares,
+        }
+    }
+
+    // Forward selection of hinge functions: knots are added one at a time, each at the location
+    // that lowers the residual sum of squares the most, until none pays for its penalty.
+    fn fit_continuous(&self, values: &[f64]) -> TrendFit {
+        let n = values.len();
+        let penalty = self.penalty(values);
+        let mut basis = linear_basis(n);
+        let mut knots: Vec<usize> = Vec::new();
+        let mut residuals = residuals_of(&basis, values);
+        while let Some((reduction, knot)) =
+            best_knot(&basis, &residuals, &knots, self.min_segment_length)
+        {
+            if reduction <= penalty {
+                break;
+            }
+            basis.push(&hinge(n, knot));
+            knots.push(knot);
+            residuals = residuals_of(&basis, values);
+        }
+        knots.sort();
+        let fitted = basis.fitted(values);
+        let mut boundaries = vec![0];
+        boundaries.extend(knots.iter().cloned());
+        boundaries.push(n);
+        let segments: Vec<TrendSegment> = boundaries
+            .windows(2)
+            .map(|pair| {
+                let (start, end) = (pair[0], pair[1]);
+                // Hinges bend after the knot, so each piece is read off between its knots.
+                let last = if end == n { n - 1 } else { end };
+                let slope = (fitted[last] - fitted[start]) / (last - start) as f64;
+                TrendSegment {
+                    start,
+                    end,
+                    intercept: fitted[start] - slope * start as f64,
+                    slope,
+                }
+            })
+            .collect();
+        TrendFit {
+            changes: changes_between(&segments),
+            segments,
+            residual_sum_of_squares: dot(&residuals, &residuals),
+        }
+    }
+
+    // The single slope change lowering the residual sum of squares of one line the most.
+    fn best_single_change(&self, values: &[f64]) -> (f64, usize) {
+        let n = values.len();
+        let min_len = self.min_segment_length;
+        match self.continuity {
+            Continuity::Continuous => {
+                let basis = linear_basis(n);
+                let residuals = residuals_of(&basis, values);
+                best_knot(&basis, &residuals, &[], min_len).unwrap_or((0.0, min_len))
+            }
+            Continuity::Discontinuous => {
+                let sums = LineSums::new(values);
+                let total = sums.line(0, n).residual_sum_of_squares;
+                (min_len..(n - min_len + 1))
+                    .map(|location| {
+                        let split = sums.line(0, location).residual_sum_of_squares +
+                            sums.line(location, n).residual_sum_of_squares;
+                        ((total - split).max(0.0), location)
+                    })
+                    .fold((-1.0, 0), |best, candidate| if candidate.0 > best.0 {
+                        candidate
+                    } else {
+                        best
+                    })
+            }
+        }
+    }
+}
+
+impl<F: Float> ChangePointDetector<NonNaN<F>> for TrendDetector {
+    type Statistic = NonNaN<F>;
+
+    fn find_candidate(&self, observations: &[NonNaN<F>]) -> Result<BestCandidate<NonNaN<F>>> {
+        let values = to_f64s(observations);
+        self.check_length(values.len())?;
+        let (reduction, location) = self.best_single_change(&values);
+        Ok(BestCandidate {
+            statistic: from_f64(reduction),
+            location,
+        })
+    }
+
+    fn min_observations(&self) -> usize {
+        self.min_segment_length * 2
+    }
+
+    fn name(&self) -> String {
+        "trend".to_string()
+    }
+
+    fn parameters(&self) -> Vec<(String, String)> {
+        let continuity = match self.continuity {
+            Continuity::Continuous => "continuous",
+            Continuity::Discontinuous => "discontinuous",
+        };
+        let mut parameters = vec![
+            ("min_segment_length".to_string(), self.min_segment_length.to_string()),
+            ("continuity".to_string(), continuity.to_string()),
+        ];
+        if let Some(penalty) = self.penalty {
+            parameters.push(("penalty".to_string(), penalty.to_string()));
+        }
+        parameters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::distributions::{IndependentSample, Normal};
+    use mersenne_twister::MersenneTwister;
+
+    // A noisy trend with slope 0.1 which bends to slope 0.6 at index 120, optionally jumping by
+    // `jump` at the same time.
+    fn bent_trend(jump: f64) -> Vec<NonNaN<f64>> {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let noise = Normal::new(0.0, 1.0);
+        (0..300)
+            .map(|t| {
+                let t = t as f64;
+                let trend = if t <= 120.0 {
+                    5.0 + 0.1 * t
+                } else {
+                    5.0 + 12.0 + jump + 0.6 * (t - 120.0)
+                };
+                NonNaN::new(trend + noise.ind_sample(&mut rng)).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn line_sums_fit_exact_lines() {
+        let values: Vec<f64> = (0..10).map(|t| 3.0 + 2.0 * t as f64).collect();
+        let line = LineSums::new(&values).line(2, 9);
+        assert!((line.slope - 2.0).abs() < 1e-9);
+        assert!((line.intercept - 3.0).abs() < 1e-9);
+        assert!(line.residual_sum_of_squares < 1e-9);
+    }
+
+    #[test]
+    fn continuous_fit_finds_slope_change() {
+        let fit = TrendDetector::new(20, Continuity::Continuous)
+            .fit(&bent_trend(0.0))
+            .unwrap();
+        assert_eq!(fit.changes.len(), 1);
+        let change = &fit.changes[0];
+        assert!((change.location as i64 - 120).abs() < 10);
+        assert!((change.slope_before - 0.1).abs() < 0.05);
+        assert!((change.slope_after - 0.6).abs() < 0.05);
+    }
+
+    #[test]
+    fn discontinuous_fit_finds_jump_and_slope_change() {
+        let fit = TrendDetector::new(20, Continuity::Discontinuous)
+            .fit(&bent_trend(-30.0))
+            .unwrap();
+        assert_eq!(fit.segments.len(), 2);
+        let change = &fit.changes[0];
+        assert!((change.location as i64 - 121).abs() < 3);
+        assert!((change.slope_before - 0.1).abs() < 0.05);
+        assert!((change.slope_after - 0.6).abs() < 0.05);
+    }
+
+    #[test]
+    fn straight_trend_has_no_changes() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let noise = Normal::new(0.0, 2.0);
+        let input: Vec<NonNaN<f64>> = (0..300)
+            .map(|t| NonNaN::new(0.3 * t as f64 + noise.ind_sample(&mut rng)).unwrap())
+            .collect();
+        for continuity in &[Continuity::Continuous, Continuity::Discontinuous] {
+            let fit = TrendDetector::new(20, *continuity).fit(&input).unwrap();
+            assert!(fit.changes.is_empty());
+            assert!((fit.segments[0].slope - 0.3).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn integer_staircase_only_changes_where_its_slope_does() {
+        // Most first differences are zero, so their MAD is zero as well.
+        let input: Vec<NonNaN<f64>> = (0..300)
+            .map(|t| {
+                let steps = if t < 150 { t / 10 } else { 15 + (t - 150) / 2 };
+                NonNaN::new(steps as f64).unwrap()
+            })
+            .collect();
+        for continuity in &[Continuity::Continuous, Continuity::Discontinuous] {
+            let fit = TrendDetector::new(20, *continuity).fit(&input).unwrap();
+            assert_eq!(fit.changes.len(), 1, "{:?}", continuity);
+            assert!((fit.changes[0].location as i64 - 150).abs() < 10);
+        }
+        let flat = vec![NonNaN::new(3.0).unwrap(); 100];
+        let fit = TrendDetector::new(10, Continuity::Discontinuous).fit(&flat).unwrap();
+        assert!(fit.changes.is_empty());
+    }
+
+    #[test]
+    fn single_change_candidate() {
+        let best_candidate = TrendDetector::new(20, Continuity::Continuous)
+            .find_candidate(&bent_trend(0.0))
+            .unwrap();
+        assert!((best_candidate.location as i64 - 120).abs() < 10);
+    }
+}
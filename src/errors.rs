@@ -1,3 +1,6 @@
+// The foreign links expand to calls of the deprecated `description` and `cause` methods.
+#![allow(deprecated)]
+
 error_chain! {
     // The type defined for this error. These are the conventional
     // and recommended names, but they can be arbitrarily chosen.
@@ -38,7 +41,9 @@ error_chain! {
     // This section can be empty.
     foreign_links {
         // Fmt(::std::fmt::Error);
-        // Io(::std::io::Error) #[cfg(unix)];
+        Io(::std::io::Error);
+        Csv(::csv::Error);
+        Json(::serde_json::Error) #[cfg(feature = "serde")];
     }
 
     // Define additional `ErrorKind` variants.  Define custom responses with the
@@ -48,18 +53,111 @@ error_chain! {
             description("Floating point value passed was either infinite or NaN")
             display("{} is not a finite floating point number", v)
         }
-        NotEnoughValues(collection_len: usize, delta: usize) {
-            description("Collection is too small -- it must have a length at least twice the value of delta")
+        NonFiniteValueAt(index: usize, v: String) {
+            description("Floating point value passed was either infinite or NaN")
+            display("{} at index {} is not a finite floating point number", v, index)
+        }
+        UnparsableInput(line: u64, column: String, v: String) {
+            description("Input value could not be parsed")
+            display("Line {}: {:?} in column {} could not be parsed", line, v, column)
+        }
+        NonFiniteInput(line: u64, column: String, v: String) {
+            description("Input value was either infinite or NaN")
+            display("Line {}: {} in column {} is not a finite floating point number", line, v, column)
+        }
+        MissingColumn(column: String) {
+            description("Input does not have the requested column")
+            display("Input has no column {}", column)
+        }
+        NotEnoughValues(collection_len: usize, min_observations: usize) {
+            description("Collection is too small for the detector")
+            display(
+                "The collection has {} elements, but the detector needs at least {}",
+                collection_len,
+                min_observations)
+        }
+        TooManyValues(collection_len: usize, max_observations: usize) {
+            description("Collection is too large for the detector")
             display(
-                "The collection has {} elements, but it needs to have at least {} elements to be used with the given value of {} for delta",
+                "The collection has {} elements, but the detector accepts at most {}",
                 collection_len,
-                delta * 2,
-                delta)
+                max_observations)
+        }
+        Cancelled {
+            description("The computation was cancelled")
+            display("The computation was cancelled or ran past its deadline")
+        }
+        PermutationTestCancelled(completed: usize, requested: usize, partial_p_value: f64) {
+            description("The permutation test was cancelled")
+            display(
+                "The permutation test was cancelled after {} of {} permutations, with a partial p-value of {}",
+                completed,
+                requested,
+                partial_p_value)
+        }
+        DetectorPanicked(message: String) {
+            description("The detector panicked")
+            display("The detector panicked: {}", message)
+        }
+        NoAsymptoticDistribution {
+            description("The detector has no known asymptotic null distribution")
+            display("The detector's statistic has no known asymptotic null distribution")
         }
         PermutationNeverRan {
             description("Permutation failed to run")
             display("Permutation failed to run")
         }
+        NotEnoughPeriods(collection_len: usize, period: usize) {
+            description("Collection is too small -- it must cover at least two full seasonal periods")
+            display(
+                "The collection has {} elements, but it needs to have at least {} elements to remove a seasonal period of {}",
+                collection_len,
+                period * 2,
+                period)
+        }
+        LengthMismatch(timestamps_len: usize, values_len: usize) {
+            description("Timestamps and values must have the same length")
+            display("There are {} timestamps but {} values", timestamps_len, values_len)
+        }
+        UnorderedTimestamps(index: usize, previous: i64, found: i64) {
+            description("Timestamps must be in non-decreasing order")
+            display(
+                "Timestamp {} at index {} comes before the previous timestamp {}",
+                found,
+                index,
+                previous)
+        }
+        DuplicateTimestamp(index: usize, timestamp: i64) {
+            description("Timestamps must be unique")
+            display("Timestamp {} at index {} is a duplicate", timestamp, index)
+        }
+        EmptyBucket(timestamp: i64) {
+            description("A resampling interval contains no observations")
+            display("The interval starting at {} contains no finite observations", timestamp)
+        }
+        UnknownDetector(name: String, known: String) {
+            description("No detector is registered under the name")
+            display("Unknown detector {:?}, expected one of {}", name, known)
+        }
+        InvalidParameter(name: String, reason: String) {
+            description("A parameter was outside of its valid range")
+            display("Invalid value for {}: {}", name, reason)
+        }
+        DimensionMismatch(row: usize, expected: usize, found: usize) {
+            description("Multivariate observations must all be non-empty and have the same dimension")
+            display(
+                "Observation {} has {} dimensions, but {} were expected",
+                row,
+                found,
+                expected)
+        }
+        UnsupportedSchemaVersion(found: u32, supported: u32) {
+            description("Serialized data uses an unsupported schema version")
+            display(
+                "Serialized data has schema version {}, but only version {} is supported",
+                found,
+                supported)
+        }
         // InvalidToolchainName(t: String) {
         //     description("invalid toolchain name")
         //     display("invalid toolchain name: '{}'", t)
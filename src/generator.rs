@@ -0,0 +1,409 @@
+//! Synthetic series with known changepoints, for testing and benchmarking detectors.
+//!
+//! A series is made of `Segment`s, each with its own mean, variance, noise distribution and
+//! trend, and `SeriesGenerator` can add AR(1) noise, seasonality and outliers on top:
+//!
+//! ```
+//! # extern crate changepoint;
+//! # extern crate mersenne_twister;
+//! # extern crate rand;
+//! # use changepoint::generator::{Family, Segment, SeriesGenerator};
+//! # use mersenne_twister::MersenneTwister;
+//! # use rand::SeedableRng;
+//! # fn main() {
+//! let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+//! let series = SeriesGenerator::new(vec![
+//!     Segment::new(500, 10.0).with_variance(25.0),
+//!     Segment::new(200, 20.0).with_variance(25.0).with_family(Family::Laplace),
+//! ]).with_ar1(0.3)
+//!     .generate(&mut rng)
+//!     .unwrap();
+//! assert_eq!(series.changepoints, vec![500]);
+//! # }
+//! ```
+
+use std::f64::consts::PI;
+use algo::non_nan::NonNaN;
+use rand::{Open01, Rng};
+use rand::distributions::{Exp, IndependentSample, Normal, StudentT};
+
+use errors::*;
+
+/// The distribution of the noise around the mean of a segment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Family {
+    Normal,
+    Laplace,
+    /// Student's t with the given degrees of freedom, which must be above 2 for the variance to
+    /// exist.
+    StudentT(f64),
+    /// Counts with the mean of the segment, whose variance is the mean rather than the variance
+    /// of the segment. Means above `POISSON_NORMAL_APPROXIMATION_MEAN` are drawn from a rounded
+    /// normal approximation. So that counts stay integers, Poisson segments take no slope and
+    /// cannot be combined with AR(1) noise, seasonality or outliers.
+    Poisson,
+    /// Exponential noise shifted to have mean zero, skewed towards large values.
+    Exponential,
+}
+
+/// A stretch of observations drawn around the same mean.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Segment {
+    pub length: usize,
+    pub mean: f64,
+    pub variance: f64,
+    pub family: Family,
+    /// Added to the mean for every observation into the segment.
+    pub slope: f64,
+}
+
+impl Segment {
+    /// Normal observations with unit variance.
+    pub fn new(length: usize, mean: f64) -> Self {
+        Segment {
+            length,
+            mean,
+            variance: 1.0,
+            family: Family::Normal,
+            slope: 0.0,
+        }
+    }
+
+    pub fn with_variance(mut self, variance: f64) -> Self {
+        self.variance = variance;
+        self
+    }
+
+    pub fn with_family(mut self, family: Family) -> Self {
+        self.family = family;
+        self
+    }
+
+    pub fn with_slope(mut self, slope: f64) -> Self {
+        self.slope = slope;
+        self
+    }
+
+    fn check(&self) -> Result<()> {
+        let invalid = |name: &str, reason: &str| -> Result<()> {
+            Err(ErrorKind::InvalidParameter(name.to_string(), reason.to_string()).into())
+        };
+        if self.length == 0 {
+            return invalid("length", "segments need at least one observation");
+        }
+        if !self.mean.is_finite() || !self.slope.is_finite() {
+            return invalid("mean", "the mean and slope must be finite");
+        }
+        if !(self.variance >= 0.0 && self.variance.is_finite()) {
+            return invalid("variance", "must be finite and not negative");
+        }
+        match self.family {
+            Family::StudentT(degrees_of_freedom)
+                if degrees_of_freedom.is_nan() || degrees_of_freedom <= 2.0 => {
+                invalid("family", "Student's t needs more than 2 degrees of freedom")
+            }
+            Family::Poisson if self.mean < 0.0 || self.slope != 0.0 => {
+                invalid("family", "Poisson segments need a mean of at least 0 and no slope")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Noise with mean zero and the variance of the segment, or of its mean for Poisson counts.
+    fn noise<R: Rng>(&self, rng: &mut R) -> f64 {
+        let standard_deviation = self.variance.sqrt();
+        match self.family {
+            Family::Normal => Normal::new(0.0, standard_deviation).ind_sample(rng),
+            Family::Laplace => {
+                let Open01(uniform) = rng.gen::<Open01<f64>>();
+                let scale = standard_deviation / 2f64.sqrt();
+                if uniform < 0.5 {
+                    scale * (2.0 * uniform).ln()
+                } else {
+                    -scale * (2.0 * (1.0 - uniform)).ln()
+                }
+            }
+            Family::StudentT(degrees_of_freedom) => {
+                let scale = standard_deviation *
+                    ((degrees_of_freedom - 2.0) / degrees_of_freedom).sqrt();
+                scale * StudentT::new(degrees_of_freedom).ind_sample(rng)
+            }
+            Family::Poisson => poisson(rng, self.mean) - self.mean,
+            Family::Exponential if standard_deviation == 0.0 => 0.0,
+            Family::Exponential => {
+                Exp::new(1.0 / standard_deviation).ind_sample(rng) - standard_deviation
+            }
+        }
+    }
+}
+
+/// The mean above which Poisson counts are drawn from a normal approximation, since exact draws
+/// take time proportional to the mean.
+pub const POISSON_NORMAL_APPROXIMATION_MEAN: f64 = 1_000.0;
+
+// Knuth's method, over pieces of the mean small enough for exp(-mean) not to underflow, or a
+// rounded normal with the same mean and variance for large means.
+fn poisson<R: Rng>(rng: &mut R, mean: f64) -> f64 {
+    if mean > POISSON_NORMAL_APPROXIMATION_MEAN {
+        return Normal::new(mean, mean.sqrt()).ind_sample(rng).round().max(0.0);
+    }
+    let mut count = 0.0;
+    let mut remaining = mean;
+    while remaining > 0.0 {
+        let piece = remaining.min(30.0);
+        remaining -= piece;
+        let threshold = (-piece).exp();
+        let mut product: f64 = rng.gen();
+        while product > threshold {
+            count += 1.0;
+            product *= rng.gen::<f64>();
+        }
+    }
+    count
+}
+
+/// A generated series with the ground truth it was generated from.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SyntheticSeries {
+    pub values: Vec<NonNaN<f64>>,
+    /// The index of the first observation of every segment but the first.
+    pub changepoints: Vec<usize>,
+    /// The indices of the injected outliers.
+    pub outliers: Vec<usize>,
+}
+
+/// Generates series from their segments, with optional AR(1) noise, seasonality and outliers.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SeriesGenerator {
+    segments: Vec<Segment>,
+    ar_coefficient: f64,
+    seasonality: Option<(usize, f64)>,
+    outliers: Option<(f64, f64)>,
+}
+
+impl SeriesGenerator {
+    pub fn new(segments: Vec<Segment>) -> Self {
+        SeriesGenerator {
+            segments,
+            ar_coefficient: 0.0,
+            seasonality: None,
+            outliers: None,
+        }
+    }
+
+    /// Correlates the noise of consecutive observations with the given coefficient, keeping the
+    /// variance of every segment.
+    ///
+    /// The noise carries over segment boundaries, so the first observations of a segment still
+    /// lean towards the end of the previous one.
+    pub fn with_ar1(mut self, coefficient: f64) -> Self {
+        self.ar_coefficient = coefficient;
+        self
+    }
+
+    /// Adds a sine wave with the given period and amplitude.
+    pub fn with_seasonality(mut self, period: usize, amplitude: f64) -> Self {
+        self.seasonality = Some((period, amplitude));
+        self
+    }
+
+    /// Shifts every observation by `magnitude` up or down with the given probability.
+    pub fn with_outliers(mut self, probability: f64, magnitude: f64) -> Self {
+        self.outliers = Some((probability, magnitude));
+        self
+    }
+
+    fn check(&self) -> Result<()> {
+        let invalid = |name: &str, reason: &str| -> Result<()> {
+            Err(ErrorKind::InvalidParameter(name.to_string(), reason.to_string()).into())
+        };
+        if self.segments.is_empty() {
+            return invalid("segments", "there must be at least one segment");
+        }
+        for segment in &self.segments {
+            segment.check()?;
+        }
+        if self.ar_coefficient.is_nan() || self.ar_coefficient.abs() >= 1.0 {
+            return invalid("ar_coefficient", "must be between -1 and 1, exclusive");
+        }
+        if let Some((period, amplitude)) = self.seasonality {
+            if period < 2 || !amplitude.is_finite() {
+                return invalid(
+                    "seasonality",
+                    "needs a period of at least 2 and a finite amplitude",
+                );
+            }
+        }
+        if let Some((probability, magnitude)) = self.outliers {
+            if !(0.0..=1.0).contains(&probability) || !magnitude.is_finite() {
+                return invalid(
+                    "outliers",
+                    "needs a probability between 0 and 1 and a finite magnitude",
+                );
+            }
+        }
+        // Anything added on top of the segments would turn Poisson counts into non-integers.
+        if self.segments.iter().any(|segment| segment.family == Family::Poisson) {
+            if self.ar_coefficient != 0.0 {
+                return invalid("ar_coefficient", "Poisson segments cannot have AR(1) noise");
+            }
+            if self.seasonality.is_some() {
+                return invalid("seasonality", "Poisson segments cannot have seasonality");
+            }
+            if self.outliers.is_some() {
+                return invalid("outliers", "Poisson segments cannot have outliers");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn generate<R: Rng>(&self, rng: &mut R) -> Result<SyntheticSeries> {
+        self.check()?;
+        let innovation_scale = (1.0 - self.ar_coefficient * self.ar_coefficient).sqrt();
+        let mut values = Vec::new();
+        let mut changepoints = Vec::new();
+        let mut outliers = Vec::new();
+        let mut noise = 0.0;
+        for segment in &self.segments {
+            if !values.is_empty() {
+                changepoints.push(values.len());
+            }
+            for offset in 0..segment.length {
+                let t = values.len();
+                noise = self.ar_coefficient * noise + innovation_scale * segment.noise(rng);
+                let seasonal = match self.seasonality {
+                    Some((period, amplitude)) => {
+                        amplitude * (2.0 * PI * t as f64 / period as f64).sin()
+                    }
+                    None => 0.0,
+                };
+                let mut value = segment.mean + segment.slope * offset as f64 + seasonal + noise;
+                if let Some((probability, magnitude)) = self.outliers {
+                    if rng.gen::<f64>() < probability {
+                        value += if rng.gen() { magnitude } else { -magnitude };
+                        outliers.push(t);
+                    }
+                }
+                values.push(NonNaN::new(value).ok_or_else(|| {
+                    Error::from(ErrorKind::NaNOrInfiniteFloat(value.to_string()))
+                })?);
+            }
+        }
+        Ok(SyntheticSeries {
+            values,
+            changepoints,
+            outliers,
+        })
+    }
+}
+
+/// Normal observations from segments given as length, mean and standard deviation, for tests.
+#[cfg(test)]
+pub(crate) fn normal_series<R: Rng>(
+    rng: &mut R,
+    segments: &[(usize, f64, f64)],
+) -> Vec<NonNaN<f64>> {
+    let segments = segments
+        .iter()
+        .map(|&(length, mean, std)| Segment::new(length, mean).with_variance(std * std))
+        .collect();
+    SeriesGenerator::new(segments).generate(rng).unwrap().values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mersenne_twister::MersenneTwister;
+    use rand::SeedableRng;
+
+    fn moments(values: &[NonNaN<f64>]) -> (f64, f64) {
+        let n = values.len() as f64;
+        let mean = values.iter().map(|value| value.value()).sum::<f64>() / n;
+        let variance = values
+            .iter()
+            .map(|value| (value.value() - mean) * (value.value() - mean))
+            .sum::<f64>() / n;
+        (mean, variance)
+    }
+
+    #[test]
+    fn families_have_the_requested_mean_and_variance() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        for &family in &[
+            Family::Normal,
+            Family::Laplace,
+            Family::StudentT(5.0),
+            Family::Exponential,
+        ]
+        {
+            let segment = Segment::new(20_000, 3.0).with_variance(4.0).with_family(family);
+            let series = SeriesGenerator::new(vec![segment]).generate(&mut rng).unwrap();
+            let (mean, variance) = moments(&series.values);
+            assert!((mean - 3.0).abs() < 0.1, "{:?} has mean {}", family, mean);
+            assert!((variance - 4.0).abs() < 0.4, "{:?} has variance {}", family, variance);
+        }
+        let poisson = Segment::new(20_000, 45.0).with_family(Family::Poisson);
+        let counts = SeriesGenerator::new(vec![poisson])
+            .generate(&mut rng)
+            .unwrap();
+        assert!(counts.values.iter().all(|value| value.value().fract() == 0.0));
+        let (mean, variance) = moments(&counts.values);
+        assert!((mean - 45.0).abs() < 0.5 && (variance - 45.0).abs() < 3.0);
+
+        let large = Segment::new(20_000, 1e6).with_family(Family::Poisson);
+        let counts = SeriesGenerator::new(vec![large])
+            .generate(&mut rng)
+            .unwrap();
+        assert!(counts.values.iter().all(|value| value.value().fract() == 0.0));
+        let (mean, variance) = moments(&counts.values);
+        assert!((mean - 1e6).abs() < 50.0 && (variance / 1e6 - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn reports_ground_truth_and_rejects_bad_parameters() {
+        let mut rng: MersenneTwister = SeedableRng::from_seed(0x1234);
+        let series = SeriesGenerator::new(vec![
+            Segment::new(100, 0.0),
+            Segment::new(50, 5.0).with_slope(0.1),
+            Segment::new(100, 0.0),
+        ]).with_ar1(0.5)
+            .with_seasonality(12, 2.0)
+            .with_outliers(0.05, 20.0)
+            .generate(&mut rng)
+            .unwrap();
+        assert_eq!(series.values.len(), 250);
+        assert_eq!(series.changepoints, vec![100, 150]);
+        assert!(!series.outliers.is_empty());
+        assert!(series.outliers.iter().all(|&index| index < 250));
+
+        assert!(SeriesGenerator::new(vec![]).generate(&mut rng).is_err());
+        assert!(
+            SeriesGenerator::new(vec![Segment::new(10, 0.0)])
+                .with_ar1(1.0)
+                .generate(&mut rng)
+                .is_err()
+        );
+        let heavy = Segment::new(10, 0.0).with_family(Family::StudentT(2.0));
+        assert!(SeriesGenerator::new(vec![heavy]).generate(&mut rng).is_err());
+        let counts = SeriesGenerator::new(vec![
+            Segment::new(10, 0.0),
+            Segment::new(10, 5.0).with_family(Family::Poisson),
+        ]);
+        for &(ref generator, parameter) in &[
+            (counts.clone().with_ar1(0.5), "ar_coefficient"),
+            (counts.clone().with_seasonality(4, 1.0), "seasonality"),
+            (counts.clone().with_outliers(0.1, 3.0), "outliers"),
+        ]
+        {
+            match *generator.generate(&mut rng).unwrap_err().kind() {
+                ErrorKind::InvalidParameter(ref name, _) => assert_eq!(name, parameter),
+                ref other => panic!("Unexpected error {:?}", other),
+            }
+        }
+    }
+}
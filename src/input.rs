@@ -0,0 +1,235 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use csv::{ReaderBuilder, StringRecord, Trim};
+use algo::non_nan::NonNaN;
+use algo::time_series::{DuplicatePolicy, TimeSeries};
+
+use errors::*;
+
+/// A column of delimited input, by header name or by zero-based position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Column::Name(ref name) => write!(f, "{:?}", name),
+            Column::Index(index) => write!(f, "#{}", index),
+        }
+    }
+}
+
+/// Reads detector input from CSV, TSV or any other single-byte delimited text, such as a file
+/// or stdin.
+///
+/// Every value is checked as it is read, and values which are unparsable, NaN or infinite are
+/// reported with their line number and column.
+#[derive(Clone, Debug)]
+pub struct CsvInput {
+    value_column: Column,
+    timestamp_column: Option<Column>,
+    delimiter: u8,
+    has_headers: bool,
+    duplicates: DuplicatePolicy,
+}
+
+impl CsvInput {
+    /// Comma separated input with a header row, reading values from `value_column`.
+    pub fn new(value_column: Column) -> Self {
+        CsvInput {
+            value_column,
+            timestamp_column: None,
+            delimiter: b',',
+            has_headers: true,
+            duplicates: DuplicatePolicy::Reject,
+        }
+    }
+
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn with_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// The column of integer timestamps used by `read_series`.
+    pub fn with_timestamp_column(mut self, timestamp_column: Column) -> Self {
+        self.timestamp_column = Some(timestamp_column);
+        self
+    }
+
+    pub fn with_duplicates(mut self, duplicates: DuplicatePolicy) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    fn resolve(&self, column: &Column, headers: Option<&StringRecord>) -> Result<usize> {
+        match (column, headers) {
+            (Column::Index(index), _) => Ok(*index),
+            (Column::Name(name), Some(headers)) => {
+                headers.iter().position(|header| header == name).ok_or_else(|| {
+                    ErrorKind::MissingColumn(column.to_string()).into()
+                })
+            }
+            (Column::Name(_), None) => Err(ErrorKind::MissingColumn(column.to_string()).into()),
+        }
+    }
+
+    // Calls `handle_row` with the line number and the requested fields of every row.
+    fn read_rows<R, H>(&self, reader: R, columns: &[&Column], mut handle_row: H) -> Result<()>
+    where
+        R: Read,
+        H: FnMut(u64, Vec<&str>) -> Result<()>,
+    {
+        let mut csv_reader = ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .flexible(true)
+            .trim(Trim::All)
+            .from_reader(reader);
+        let headers = if self.has_headers {
+            Some(csv_reader.headers()?.clone())
+        } else {
+            None
+        };
+        let positions: Vec<usize> = columns
+            .iter()
+            .map(|column| self.resolve(column, headers.as_ref()))
+            .collect::<Result<_>>()?;
+        for record in csv_reader.records() {
+            let record = record?;
+            let line = record.position().map(|position| position.line()).unwrap_or(0);
+            let fields = positions
+                .iter()
+                .zip(columns.iter())
+                .map(|(&position, column)| {
+                    record.get(position).ok_or_else(|| {
+                        ErrorKind::MissingColumn(format!("{} on line {}", column, line)).into()
+                    })
+                })
+                .collect::<Result<Vec<&str>>>()?;
+            handle_row(line, fields)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_values<R: Read>(&self, reader: R) -> Result<Vec<NonNaN<f64>>> {
+        let mut values = Vec::new();
+        self.read_rows(reader, &[&self.value_column], |line, fields| {
+            values.push(parse_value(line, &self.value_column, fields[0])?);
+            Ok(())
+        })?;
+        Ok(values)
+    }
+
+    /// Reads timestamps and values, which requires a timestamp column to have been set.
+    pub fn read_series<R: Read>(&self, reader: R) -> Result<TimeSeries<NonNaN<f64>>> {
+        let timestamp_column = self.timestamp_column.as_ref().ok_or_else(|| {
+            Error::from(ErrorKind::InvalidParameter(
+                "timestamp_column".to_string(),
+                "a timestamp column is needed to read a series".to_string(),
+            ))
+        })?;
+        let mut timestamps = Vec::new();
+        let mut values = Vec::new();
+        self.read_rows(
+            reader,
+            &[timestamp_column, &self.value_column],
+            |line, fields| {
+                let timestamp = fields[0].parse::<i64>().map_err(|_| {
+                    Error::from(ErrorKind::UnparsableInput(
+                        line,
+                        timestamp_column.to_string(),
+                        fields[0].to_string(),
+                    ))
+                })?;
+                timestamps.push(timestamp);
+                values.push(parse_value(line, &self.value_column, fields[1])?);
+                Ok(())
+            },
+        )?;
+        TimeSeries::with_duplicates(timestamps, values, self.duplicates)
+    }
+
+    pub fn read_values_from_path<P: AsRef<Path>>(&self, path: P) -> Result<Vec<NonNaN<f64>>> {
+        self.read_values(File::open(path)?)
+    }
+
+    pub fn read_series_from_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<TimeSeries<NonNaN<f64>>> {
+        self.read_series(File::open(path)?)
+    }
+}
+
+fn parse_value(line: u64, column: &Column, field: &str) -> Result<NonNaN<f64>> {
+    let value = field.parse::<f64>().map_err(|_| {
+        Error::from(ErrorKind::UnparsableInput(
+            line,
+            column.to_string(),
+            field.to_string(),
+        ))
+    })?;
+    NonNaN::new(value).ok_or_else(|| {
+        ErrorKind::NonFiniteInput(line, column.to_string(), field.to_string()).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(input: &CsvInput, text: &str) -> Result<Vec<f64>> {
+        input.read_values(text.as_bytes()).map(|values| {
+            values.iter().map(|value| value.value()).collect()
+        })
+    }
+
+    #[test]
+    fn reads_columns_by_name_or_index() {
+        let text = "time,latency\n1,0.5\n2, 0.75\n3,1e3\n";
+        let by_name = CsvInput::new(Column::Name("latency".to_string()));
+        assert_eq!(values(&by_name, text).unwrap(), vec![0.5, 0.75, 1000.0]);
+        let tsv = CsvInput::new(Column::Index(1))
+            .with_delimiter(b'\t')
+            .with_headers(false);
+        assert_eq!(values(&tsv, "1\t2.5\n2\t3.5\n").unwrap(), vec![2.5, 3.5]);
+        let missing = CsvInput::new(Column::Name("cpu".to_string()));
+        assert!(values(&missing, text).is_err());
+    }
+
+    #[test]
+    fn reads_timestamped_series() {
+        let text = "time,latency\n10,0.5\n20,0.75\n35,1.0\n";
+        let series = CsvInput::new(Column::Name("latency".to_string()))
+            .with_timestamp_column(Column::Name("time".to_string()))
+            .read_series(text.as_bytes())
+            .unwrap();
+        assert_eq!(series.timestamps(), &[10, 20, 35]);
+        assert_eq!(series.values()[2].value(), 1.0);
+    }
+
+    #[test]
+    fn errors_carry_line_numbers() {
+        let input = CsvInput::new(Column::Name("latency".to_string()));
+        match *values(&input, "latency\n1.0\nfast\n").unwrap_err().kind() {
+            ErrorKind::UnparsableInput(3, _, ref value) => assert_eq!(value, "fast"),
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+        match *values(&input, "latency\n1.0\n2.0\nNaN\n").unwrap_err().kind() {
+            ErrorKind::NonFiniteInput(4, _, _) => {}
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+    }
+}
@@ -1,14 +1,52 @@
 #[macro_use]
 extern crate error_chain;
+#[cfg(feature = "parallel")]
 extern crate rayon;
 extern crate mersenne_twister;
 extern crate rand;
 extern crate num;
+extern crate csv;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
 pub mod errors;
 mod algo;
+mod input;
+pub mod generator;
+#[cfg(feature = "serde")]
+mod serialization;
 
 pub use algo::edm_x::edm_x::EDMX;
+pub use algo::dispersion::{Dispersion, EDMScale};
+pub use algo::kcp::{KCP, Kernel, KernelSegmentation, ModelSelection};
+pub use algo::multivariate::{Aggregation, MultivariateEDMX, check_dimensions};
+pub use algo::trend::{Continuity, SlopeChange, TrendDetector, TrendFit, TrendSegment};
+pub use algo::seasonal::{Decomposition, Deseasonalized, Deseasonalizer, SeasonallyAdjusted, Stl};
+pub use algo::anomaly::{Direction, SeasonalHybridESD};
 pub use algo::changepoint::ChangePointDetector;
+pub use algo::registry::{BoxedDetector, DetectorRegistry, Parameters};
 pub use algo::non_nan::{NonNaN, to_non_nans};
-pub use algo::permutation_test::{permutation_test, PermutationTestResult};
+pub use algo::cleaning::{Cleaned, MissingValuePolicy, Repair, RepairAction, clean};
+pub use algo::parallelism::Parallelism;
+pub use algo::cancellation::CancellationToken;
+pub use algo::progress::{Progress, ProgressObserver};
+pub use algo::permutation_test::{permutation_test, permutation_test_with_options,
+                                 PermutationTestOptions, PermutationTestResult};
+pub use algo::batch::{BatchResult, NamedSeries, batch_permutation_test,
+                      batch_permutation_test_with_parallelism};
+pub use algo::significance::{Asymptotic, ParametricBootstrap, PermutationTest, SignificanceMethod,
+                            SignificanceTest};
+pub use algo::multiple_testing::{Correction, CorrectedResults, adjust_p_values, correct};
+pub use algo::time_series::{DuplicatePolicy, TimeSeries, TimeSpan, TimedCandidate,
+                            TimedPermutationTestResult, find_candidate_in_series,
+                            permutation_test_series};
+pub use algo::resample::{Aggregator, GapPolicy, MAX_RESAMPLED_INTERVALS, resample,
+                         resample_events};
+pub use input::{Column, CsvInput};
+#[cfg(feature = "serde")]
+pub use serialization::{SCHEMA_VERSION, Versioned, from_json, to_json};
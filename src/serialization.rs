@@ -0,0 +1,155 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use errors::*;
+
+/// The version of the JSON schema written by `to_json`, which changes whenever a serialized
+/// configuration or result changes shape.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The envelope every serialized value is stored in, so that stored detections can be checked
+/// against the schema they were written with before they are replayed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub version: u32,
+    pub data: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn new(data: T) -> Self {
+        Versioned {
+            version: SCHEMA_VERSION,
+            data,
+        }
+    }
+}
+
+// Read first, so that data from another version is reported as such rather than as a
+// malformed payload.
+#[derive(Deserialize)]
+struct VersionOnly {
+    version: u32,
+}
+
+/// Serializes a detector configuration or result as JSON, wrapped in a `Versioned` envelope.
+pub fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(&Versioned::new(value))?)
+}
+
+/// Reads a value written by `to_json`, failing if it was written with another schema version.
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    let VersionOnly { version } = serde_json::from_str(json)?;
+    if version != SCHEMA_VERSION {
+        return Err(
+            ErrorKind::UnsupportedSchemaVersion(version, SCHEMA_VERSION).into(),
+        );
+    }
+    let versioned: Versioned<T> = serde_json::from_str(json)?;
+    Ok(versioned.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::edm_x::edm_x::EDMX;
+    use algo::dispersion::EDMScale;
+    use algo::kcp::{KCP, Kernel};
+    use algo::multivariate::MultivariateEDMX;
+    use algo::trend::TrendDetector;
+    use algo::non_nan::NonNaN;
+    use algo::permutation_test::PermutationTestResult;
+    use algo::time_series::{TimeSpan, TimedCandidate};
+
+    #[test]
+    fn schema_is_stable() {
+        let result = PermutationTestResult {
+            p_value: 0.25,
+            changepoint_index: 42,
+        };
+        assert_eq!(
+            to_json(&result).unwrap(),
+            r#"{"version":1,"data":{"p_value":0.25,"changepoint_index":42}}"#
+        );
+        assert_eq!(
+            to_json(&KCP::new(Kernel::Gaussian(Some(1.5)), 5, 2)).unwrap(),
+            r#"{"version":1,"data":{"kernel":{"gaussian":1.5},"min_segment_length":5,"#
+                .to_string() + r#""max_changepoints":2,"model_selection":"slope_heuristic"}}"#
+        );
+    }
+
+    #[test]
+    fn configurations_and_results_round_trip() {
+        let json = to_json(&EDMX::new(30)).unwrap();
+        assert_eq!(json, r#"{"version":1,"data":{"delta":30}}"#);
+        assert!(from_json::<EDMX>(&json).is_ok());
+
+        let candidate = TimedCandidate {
+            statistic: NonNaN::new(12.5).unwrap(),
+            changepoint_index: 3,
+            changepoint_timestamp: 300,
+            before: Some(TimeSpan {
+                start_index: 0,
+                end_index: 3,
+                start: 0,
+                end: 200,
+            }),
+            after: TimeSpan {
+                start_index: 3,
+                end_index: 5,
+                start: 300,
+                end: 400,
+            },
+        };
+        let json = to_json(&candidate).unwrap();
+        assert_eq!(from_json::<TimedCandidate<NonNaN<f64>>>(&json).unwrap(), candidate);
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        match *from_json::<EDMX>(r#"{"version":2,"data":{"window":30}}"#)
+            .unwrap_err()
+            .kind() {
+            ErrorKind::UnsupportedSchemaVersion(2, 1) => {}
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+        assert!(from_json::<EDMX>(r#"{"version":1,"data":{"window":30}}"#).is_err());
+    }
+
+    #[test]
+    fn invalid_configurations_are_rejected() {
+        let kcp = |kernel: &str, min_segment_length: usize, max_changepoints: usize| {
+            from_json::<KCP>(&format!(
+                "{{\"version\":1,\"data\":{{\"kernel\":{},\"min_segment_length\":{},\
+                 \"max_changepoints\":{},\"model_selection\":\"slope_heuristic\"}}}}",
+                kernel,
+                min_segment_length,
+                max_changepoints
+            ))
+        };
+        assert!(kcp(r#""linear""#, 5, 1).is_ok());
+        let error = kcp(r#""linear""#, 0, 1).unwrap_err();
+        assert!(error.to_string().contains("min_segment_length"));
+        let error = kcp(r#"{"gaussian":-1.0}"#, 5, 1).unwrap_err();
+        assert!(error.to_string().contains("bandwidth"));
+        let observations: Vec<NonNaN<f64>> =
+            (0..30).map(|i| NonNaN::new((i / 10) as f64).unwrap()).collect();
+        let unbounded = kcp(r#""linear""#, 5, usize::MAX).unwrap();
+        assert_eq!(unbounded.segment(&observations).unwrap().costs.len(), 6);
+
+        assert!(from_json::<EDMX>(r#"{"version":1,"data":{"delta":0}}"#).is_err());
+        let multivariate = r#"{"version":1,"data":{"delta":0,"aggregation":"sum"}}"#;
+        assert!(from_json::<MultivariateEDMX>(multivariate).is_err());
+        let edm_scale = r#"{"version":1,"data":{"delta":0,"dispersion":"interquartile_range"}}"#;
+        assert!(from_json::<EDMScale>(edm_scale).unwrap_err().to_string().contains("delta"));
+        for min_segment_length in 0..3 {
+            let trend = format!(
+                "{{\"version\":1,\"data\":{{\"min_segment_length\":{},\
+                 \"continuity\":\"continuous\",\"penalty\":null}}}}",
+                min_segment_length
+            );
+            let trend = from_json::<TrendDetector>(&trend);
+            assert_eq!(trend.is_ok(), min_segment_length == 2);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! A wasm-bindgen API for EDM-X and the permutation test, for running detection in a browser.
+//!
+//! The library is built without its `parallel` feature, so permutations are evaluated one after
+//! another on the calling thread, with the same results as the parallel build for the same seed.
+//!
+//! ```sh
+//! wasm-pack build wasm --target web
+//! ```
+//!
+//! ```js
+//! const detector = new EDMX(30);
+//! const result = permutationTest(detector, new Float64Array(values), 199, 4660);
+//! console.log(result.changepointIndex, result.pValue);
+//! ```
+
+extern crate changepoint;
+extern crate mersenne_twister;
+extern crate rand;
+extern crate wasm_bindgen;
+
+use changepoint::{ChangePointDetector, EDMX as Detector, MissingValuePolicy, NonNaN, clean,
+                  permutation_test as run_permutation_test};
+use changepoint::errors::Result;
+use mersenne_twister::MersenneTwister;
+use rand::SeedableRng;
+use wasm_bindgen::prelude::*;
+
+/// The E-Divisive with Medians detector.
+#[wasm_bindgen]
+pub struct EDMX {
+    detector: Detector,
+}
+
+/// The most likely changepoint, as an index into the observations.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candidate {
+    #[wasm_bindgen(js_name = changepointIndex)]
+    pub changepoint_index: usize,
+    pub statistic: f64,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PermutationTestResult {
+    #[wasm_bindgen(js_name = changepointIndex)]
+    pub changepoint_index: usize,
+    #[wasm_bindgen(js_name = pValue)]
+    pub p_value: f64,
+}
+
+fn observations(values: &[f64]) -> Result<Vec<NonNaN<f64>>> {
+    clean(values, MissingValuePolicy::Error).map(|cleaned| cleaned.values)
+}
+
+fn to_js_error(error: changepoint::errors::Error) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+impl EDMX {
+    fn candidate(&self, values: &[f64]) -> Result<Candidate> {
+        let candidate = self.detector.find_candidate(&observations(values)?)?;
+        Ok(Candidate {
+            changepoint_index: candidate.location,
+            statistic: candidate.statistic.value(),
+        })
+    }
+
+    fn permutation_test(
+        &self,
+        values: &[f64],
+        permutations: usize,
+        seed: u32,
+    ) -> Result<PermutationTestResult> {
+        let rng: MersenneTwister = SeedableRng::from_seed(u64::from(seed));
+        let result =
+            run_permutation_test(&self.detector, rng, permutations, &observations(values)?)?;
+        Ok(PermutationTestResult {
+            changepoint_index: result.changepoint_index,
+            p_value: result.p_value,
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl EDMX {
+    /// A detector requiring at least `delta` observations on either side of a changepoint.
+    #[wasm_bindgen(constructor)]
+    pub fn new(delta: usize) -> EDMX {
+        EDMX { detector: Detector::new(delta) }
+    }
+
+    /// Finds the most likely changepoint in a `Float64Array` of finite values.
+    #[wasm_bindgen(js_name = findCandidate)]
+    pub fn find_candidate(&self, values: &[f64]) -> ::std::result::Result<Candidate, JsError> {
+        self.candidate(values).map_err(to_js_error)
+    }
+}
+
+/// Tests whether the changepoint found by `detector` is significant, against `permutations`
+/// shuffles of the values drawn with the given `seed`.
+#[wasm_bindgen(js_name = permutationTest)]
+pub fn permutation_test(
+    detector: &EDMX,
+    values: &[f64],
+    permutations: usize,
+    seed: u32,
+) -> ::std::result::Result<PermutationTestResult, JsError> {
+    detector.permutation_test(values, permutations, seed).map_err(
+        to_js_error,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_and_tests_changepoints() {
+        let values: Vec<f64> = (0..120)
+            .map(|i| if i < 60 { 10.0 } else { 20.0 } + (i % 5) as f64 * 0.5)
+            .collect();
+        let detector = EDMX::new(10);
+        let candidate = detector.candidate(&values).unwrap();
+        assert!((candidate.changepoint_index as i64 - 60).abs() <= 2);
+        let result = detector.permutation_test(&values, 19, 7).unwrap();
+        assert_eq!(result.changepoint_index, candidate.changepoint_index);
+        assert!(result.p_value <= 0.05);
+        assert!(detector.candidate(&[1.0, f64::NAN]).is_err());
+    }
+}